@@ -1,5 +1,6 @@
-use serde::{Deserialize, Serialize};
-use std::net::IpAddr;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv6Addr};
 
 // --- GÜNCELLENMİŞ KURAL YAPILARI ---
 
@@ -10,6 +11,73 @@ pub enum Action {
     Allow,
     Block,
     BypassCache,
+    /// Splice the raw TLS bytes of a `CONNECT` without intercepting them.
+    Tunnel,
+    /// Allow and cache, but cap upstream downloads of all matching traffic at this many bytes per second.
+    Throttle(u64),
+    /// Allow and cache, editing request headers before the cache lookup and response headers before storing.
+    RewriteHeaders(HeaderRewrite),
+    /// Send the request to this URL instead, uncached. A URL ending in `/` is a prefix the request path is appended to.
+    MapRemote(String),
+    /// Answer from this file, or from the request path under this directory, without contacting the origin.
+    MapLocal(String),
+    /// Answer with a synthesized response without contacting the origin.
+    Mock(MockResponse),
+}
+
+/// Response synthesized by a `mock` rule.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct MockResponse {
+    pub status: u16,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Inline body; ignored when `body_file` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// Read on every request, so the file can be edited while the proxy runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_file: Option<String>,
+    /// Artificial latency before the response is sent.
+    pub delay_ms: u64,
+    /// Fraction of requests (0.0 to 1.0) answered with `failure_status` instead, for chaos testing.
+    pub failure_rate: f64,
+    pub failure_status: u16,
+}
+
+impl Default for MockResponse {
+    fn default() -> Self {
+        Self {
+            status: 200,
+            headers: BTreeMap::new(),
+            body: None,
+            body_file: None,
+            delay_ms: 0,
+            failure_rate: 0.0,
+            failure_status: 503,
+        }
+    }
+}
+
+/// Header edits of a `rewrite-headers` rule. Values may use `{{client_ip}}`, `{{user}}`,
+/// `{{host}}`, `{{method}}` and `{{url}}`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct HeaderRewrite {
+    pub request: HeaderEdits,
+    pub response: HeaderEdits,
+}
+
+/// Applied in order: `remove`, then `set` (replacing existing values), then `add` (appending).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct HeaderEdits {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remove: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub set: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub add: BTreeMap<String, String>,
 }
 
 // --- DEĞİŞİKLİK BURADA ---
@@ -25,6 +93,9 @@ pub struct Rule {
     pub name: String,
     pub condition: RuleCondition,
     pub action: Action,
+    /// Proxy users the rule applies to; empty means every client.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<String>,
 }
 
 
@@ -39,6 +110,30 @@ pub struct Stats {
     pub disk_items: u64,
     pub total_disk_size_bytes: u64,
     pub bytes_saved: u64,
+    /// Estimated time clients would have spent fetching cache hits from upstream.
+    pub time_saved_ms: u64,
+    pub upstream: Vec<UpstreamStats>,
+    pub rejected_clients: AccessRejections,
+}
+
+/// Connections and queries refused by each listener's access list.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessRejections {
+    pub proxy: u64,
+    pub dns: u64,
+    pub management: u64,
+}
+
+/// Upstream download measurements for a single domain, recorded when cache fills complete.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamStats {
+    pub domain: String,
+    pub fills: u64,
+    pub bytes_downloaded: u64,
+    pub avg_latency_ms: u64,
+    pub avg_throughput_bytes_per_sec: u64,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -50,19 +145,379 @@ pub struct Settings {
     #[serde(default)] 
     pub dns: Dns,
     #[serde(default)]
+    pub downloader: Downloader,
+    #[serde(default)]
+    pub transparent: Transparent,
+    #[serde(default)]
+    pub upstream: Upstream,
+    #[serde(default)]
+    pub shutdown: Shutdown,
+    #[serde(default)]
+    pub throttle: Throttle,
+    #[serde(default)]
+    pub resolver: Resolver,
+    #[serde(default)]
+    pub upstream_tls: UpstreamTls,
+    #[serde(default)]
+    pub error_pages: ErrorPages,
+    #[serde(default)]
+    pub har: Har,
+    #[serde(default)]
+    pub flows: Flows,
+    #[serde(default)]
     pub rules: Vec<Rule>,
 }
 
+/// HAR 1.2 recording of proxied exchanges and replay of recorded responses.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Har {
+    /// Record from startup; recording can also be toggled through the management API.
+    pub record: bool,
+    /// Oldest entries are dropped beyond this many.
+    pub max_entries: usize,
+    /// Larger request or response bodies are recorded without their content.
+    pub max_body_bytes: usize,
+    /// HAR file whose responses are served instead of contacting origins.
+    pub replay_file: Option<String>,
+    pub replay_mode: ReplayMode,
+}
+
+impl Default for Har {
+    fn default() -> Self {
+        Self {
+            record: false,
+            max_entries: 1000,
+            max_body_bytes: 10 * 1024 * 1024,
+            replay_file: None,
+            replay_mode: ReplayMode::Fallback,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayMode {
+    /// Requests missing from the HAR file fail instead of reaching the origin.
+    Strict,
+    /// Requests missing from the HAR file go through the proxy as usual.
+    Fallback,
+}
+
+/// What flow records published to the dashboard carry beyond headers and timings.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Flows {
+    /// Attach request and response bodies to flows.
+    pub capture_bodies: bool,
+    /// Only this many bytes of each body are kept; the rest is counted but dropped.
+    pub max_body_bytes: usize,
+}
+
+impl Default for Flows {
+    fn default() -> Self {
+        Self { capture_bodies: false, max_body_bytes: 64 * 1024 }
+    }
+}
+
+/// Pages returned for blocked requests and upstream failures.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ErrorPages {
+    /// Directory with `block.html`, `timeout.html`, `tls.html`, `dns.html`, `offline.html`
+    /// and `upstream.html`; missing files fall back to the built-in page.
+    pub templates_dir: Option<String>,
+}
+
+/// How origin certificates are verified and which client certificates are presented.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct UpstreamTls {
+    /// PEM file with extra CAs trusted in addition to the system roots.
+    pub ca_bundle: Option<String>,
+    /// Domains whose certificates are accepted even if verification fails; `*` wildcards are allowed.
+    pub insecure_domains: Vec<String>,
+    pub client_certs: Vec<ClientCert>,
+}
+
+/// mTLS client certificate presented to matching origins.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ClientCert {
+    /// Domain name; `*` wildcards are allowed.
+    pub domain: String,
+    /// PEM certificate chain, leaf first.
+    pub cert: String,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1).
+    pub key: String,
+}
+
+/// DNS resolution for upstream connections, independent of the system resolver
+/// (which may point at our own DNS server).
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Resolver {
+    /// `ip` or `ip:port` entries; empty falls back to the system configuration.
+    pub nameservers: Vec<String>,
+    /// Number of answers kept in the resolver cache.
+    pub cache_size: usize,
+    /// Fixed answers that win over DNS, e.g. to point a production name at a staging server.
+    pub hosts: Vec<HostOverride>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self {
+            nameservers: Vec::new(),
+            cache_size: 1024,
+            hosts: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct HostOverride {
+    /// Domain name; `*` wildcards are allowed, e.g. `*.staging.example.com`.
+    pub domain: String,
+    pub addresses: Vec<IpAddr>,
+}
+
+/// Token-bucket limits on upstream downloads, in bytes per second. Unset limits are disabled.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Throttle {
+    pub global_bytes_per_sec: Option<u64>,
+    pub per_client_bytes_per_sec: Option<u64>,
+    pub per_domain_bytes_per_sec: Option<u64>,
+    /// Apply the global and per-client limits to responses served from the cache as well.
+    pub throttle_cache_hits: bool,
+}
+
+/// HAR recorder and replayer state, served by `/api/har/status`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HarStatus {
+    pub recording: bool,
+    pub recorded_entries: usize,
+    pub replay: Option<ReplayStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayStatus {
+    pub mode: ReplayMode,
+    pub entries: usize,
+    /// Recorded responses served so far.
+    pub served: u64,
+}
+
+/// Snapshot of every active token bucket, served by `/api/throttle`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ThrottleStatus {
+    pub global: Option<BucketStatus>,
+    pub clients: Vec<BucketStatus>,
+    pub domains: Vec<BucketStatus>,
+    pub rules: Vec<BucketStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BucketStatus {
+    /// Client IP, domain or rule name; `*` for the global bucket.
+    pub key: String,
+    pub bytes_per_sec: u64,
+    pub bytes_transferred: u64,
+    /// Total time streams spent waiting on this bucket.
+    pub delayed_ms: u64,
+}
+
+/// How long in-flight requests and cache fills may run after Ctrl+C or SIGTERM.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Shutdown {
+    pub grace_period_secs: u64,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self { grace_period_secs: 30 }
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Proxy {
     pub port: u16,
-    pub bind_address: String,
+    pub bind_address: BindAddresses,
+    /// Publish every tunnelled WebSocket frame to the dashboard, not just per-flow counts.
+    #[serde(default)]
+    pub inspect_websocket_frames: bool,
+    #[serde(default)]
+    pub tls_passthrough: TlsPassthrough,
+    #[serde(default)]
+    pub socks5: Socks5,
+    #[serde(default)]
+    pub auth: ProxyAuth,
+    #[serde(default)]
+    pub access: AccessList,
+    #[serde(default)]
+    pub cache_headers: CacheHeaders,
+    #[serde(default)]
+    pub proxy_protocol: ProxyProtocol,
+}
+
+/// PROXY protocol (v1 text or v2 binary) headers prepended by a load balancer,
+/// carrying the real client address.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ProxyProtocol {
+    pub enabled: bool,
+    /// Peers (CIDRs or bare addresses) that must send a header; other peers connect
+    /// directly. Must not be empty when `enabled` is set.
+    pub trusted_proxies: Vec<String>,
+    pub header_timeout_secs: u64,
+}
+
+impl Default for ProxyProtocol {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            trusted_proxies: Vec::new(),
+            header_timeout_secs: 5,
+        }
+    }
+}
+
+/// Cache diagnostics headers added to responses served by a listener.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct CacheHeaders {
+    /// RFC 9211 `Cache-Status`, `Age` on hits, and `Via`.
+    pub enabled: bool,
+    /// Legacy `X-Cache: HIT | MISS | BYPASS`.
+    pub x_cache: bool,
+    /// Identifies this cache in `Cache-Status` and `Via`.
+    pub name: String,
+}
+
+impl Default for CacheHeaders {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            x_cache: false,
+            name: "sentiric".to_string(),
+        }
+    }
+}
+
+/// Client IP filter for a listener. Entries are CIDRs or bare addresses;
+/// `deny` wins over `allow`, and an empty `allow` admits every client.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AccessList {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+/// Basic `Proxy-Authorization` checked against an htpasswd-style users file.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ProxyAuth {
+    pub enabled: bool,
+    /// One `user:password` per line; passwords may be bcrypt, `{SHA}` or plain text.
+    pub users_file: String,
+    pub realm: String,
+}
+
+impl Default for ProxyAuth {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            users_file: "./users.htpasswd".to_string(),
+            realm: "Sentiric Traffic Cache".to_string(),
+        }
+    }
+}
+
+/// SOCKS5 listener started next to the HTTP proxy.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Socks5 {
+    pub enabled: bool,
+    pub port: u16,
+    /// When both are set, clients must authenticate with RFC 1929 username/password.
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub cache_headers: CacheHeaders,
+}
+
+impl Default for Socks5 {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 1080,
+            username: None,
+            password: None,
+            cache_headers: CacheHeaders::default(),
+        }
+    }
+}
+
+/// Automatic TLS passthrough for hosts that keep rejecting our leaf certificates.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct TlsPassthrough {
+    pub auto_learn: bool,
+    /// Consecutive failed handshakes after which a host is tunnelled instead of intercepted.
+    pub failure_threshold: u32,
+}
+
+impl Default for TlsPassthrough {
+    fn default() -> Self {
+        Self {
+            auto_learn: false,
+            failure_threshold: 3,
+        }
+    }
+}
+
+/// Addresses a listener binds to: IPv4 or IPv6 literals, or interface names such as
+/// `eth0`. Accepts a single string or a list in the configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BindAddresses(pub Vec<String>);
+
+impl BindAddresses {
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+}
+
+impl From<&str> for BindAddresses {
+    fn from(address: &str) -> Self {
+        Self(vec![address.to_string()])
+    }
+}
+
+impl<'de> Deserialize<'de> for BindAddresses {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            One(String),
+            Many(Vec<String>),
+        }
+        Ok(match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(address) => Self(vec![address]),
+            OneOrMany::Many(addresses) => Self(addresses),
+        })
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Management {
     pub port: u16,
-    pub bind_address: String,
+    pub bind_address: BindAddresses,
+    #[serde(default)]
+    pub access: AccessList,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -79,8 +534,13 @@ pub struct Cache {
 pub struct Dns {
     pub enabled: bool,
     pub port: u16,
-    pub bind_address: String,
+    pub bind_address: BindAddresses,
     pub response_ip: IpAddr,
+    /// Answer for `AAAA` queries; without it only an IPv6 `response_ip` produces `AAAA` records.
+    #[serde(default)]
+    pub response_ipv6: Option<Ipv6Addr>,
+    #[serde(default)]
+    pub access: AccessList,
 }
 
 impl Default for Dns {
@@ -88,12 +548,129 @@ impl Default for Dns {
         Self {
             enabled: false,
             port: 53,
-            bind_address: "0.0.0.0".to_string(),
+            bind_address: BindAddresses::from("0.0.0.0"),
             response_ip: "127.0.0.1".parse().unwrap(),
+            response_ipv6: None,
+            access: AccessList::default(),
         }
     }
 }
 
+/// Listeners for clients that reach us through DNS redirection instead of a proxy setting.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Transparent {
+    pub enabled: bool,
+    pub bind_address: BindAddresses,
+    pub http_port: u16,
+    pub https_port: u16,
+    pub cache_headers: CacheHeaders,
+}
+
+impl Default for Transparent {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: BindAddresses::from("0.0.0.0"),
+            http_port: 80,
+            https_port: 443,
+            cache_headers: CacheHeaders::default(),
+        }
+    }
+}
+
+/// Parent proxy used for egress, e.g. a mandatory corporate proxy.
+///
+/// Proxy URLs use the `http://`, `socks5://` or `socks5h://` scheme and may carry
+/// `user:password@` credentials. Without `proxy`, the standard `HTTPS_PROXY`,
+/// `HTTP_PROXY`, `ALL_PROXY` and `NO_PROXY` environment variables are honoured.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Upstream {
+    pub proxy: Option<String>,
+    /// Hosts reached directly: exact names, `.suffix` or `*` wildcards, or `*` for all.
+    pub no_proxy: Vec<String>,
+    pub use_env: bool,
+    pub overrides: Vec<UpstreamOverride>,
+}
+
+impl Default for Upstream {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            no_proxy: Vec::new(),
+            use_env: true,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct UpstreamOverride {
+    /// Domain name; `*` wildcards are allowed, e.g. `*.example.com`.
+    pub domain: String,
+    /// A proxy URL, or `direct` to bypass the parent proxy.
+    pub proxy: String,
+}
+
+/// Settings for the outbound HTTP client used on cache misses and bypasses.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Downloader {
+    /// Upper bound on simultaneous upstream requests to a single host.
+    pub max_concurrent_per_host: usize,
+    /// Per-domain protocol choices for origins that misbehave with ALPN-negotiated HTTP/2.
+    pub protocol_overrides: Vec<ProtocolOverride>,
+    pub connect_timeout_secs: u64,
+    /// Time allowed between sending a request and receiving the response headers.
+    pub ttfb_timeout_secs: u64,
+    /// Longest pause tolerated between two chunks of a response body.
+    pub idle_timeout_secs: u64,
+    /// Extra attempts for idempotent, bodiless requests that fail before a response arrives.
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled for every further attempt.
+    pub retry_backoff_ms: u64,
+    pub pool_max_idle_per_host: usize,
+    pub pool_idle_timeout_secs: u64,
+    /// Head start the first address family gets before the other one is tried (RFC 8305).
+    pub happy_eyeballs_timeout_ms: u64,
+}
+
+impl Default for Downloader {
+    fn default() -> Self {
+        Self {
+            max_concurrent_per_host: 32,
+            protocol_overrides: Vec::new(),
+            connect_timeout_secs: 10,
+            ttfb_timeout_secs: 30,
+            idle_timeout_secs: 60,
+            max_retries: 2,
+            retry_backoff_ms: 200,
+            pool_max_idle_per_host: 32,
+            pool_idle_timeout_secs: 90,
+            happy_eyeballs_timeout_ms: 300,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ProtocolOverride {
+    /// Domain name; `*` wildcards are allowed, e.g. `*.example.com`.
+    pub domain: String,
+    pub protocol: UpstreamProtocol,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpstreamProtocol {
+    /// HTTP/2 when the origin offers it via ALPN, HTTP/1.1 otherwise.
+    #[default]
+    Auto,
+    Http1,
+    /// HTTP/2 only (prior knowledge for plain `http://` origins).
+    Http2,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct CacheEntryInfo {
@@ -101,13 +678,167 @@ pub struct CacheEntryInfo {
     pub size_bytes: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct FlowEntry {
     pub id: String,
     pub method: String,
     pub uri: String,
     pub status_code: u16,
+    /// Response body bytes actually sent to the client; final once `timings.total_ms` is set.
     pub response_size_bytes: u64,
     pub is_hit: bool,
+    /// HTTP version negotiated with the client, e.g. `HTTP/1.1` or `HTTP/2.0`.
+    pub protocol: String,
+    /// HTTP version spoken with the origin, when one was contacted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_protocol: Option<String>,
+    /// Client address after any PROXY protocol header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_ip: Option<IpAddr>,
+    /// Unix time in milliseconds when the request arrived.
+    #[serde(default)]
+    pub started_at_ms: u64,
+    /// Request headers as received from the client.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub request_headers: Vec<FlowHeader>,
+    /// Response headers as sent to the client.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub response_headers: Vec<FlowHeader>,
+    #[serde(default)]
+    pub timings: FlowTimings,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheDecision>,
+    /// Name of the rule that matched the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    /// Present when `flows.capture_bodies` is on and the request had a body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<CapturedBody>,
+    /// Present when `flows.capture_bodies` is on and the response had a body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_body: Option<CapturedBody>,
+    /// Present for WebSocket tunnels; updated while the tunnel is open.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub websocket: Option<WebSocketSummary>,
+    /// Present when a `CONNECT` was spliced without TLS interception.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_passthrough: Option<PassthroughReason>,
+    /// Authenticated proxy user that made the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// Why the upstream request failed, e.g. a connect or first-byte timeout.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Present when a `mock` rule answered instead of the origin.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mock: Option<MockSummary>,
+    /// The response was served from a loaded HAR file.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub replayed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FlowHeader {
+    pub name: String,
+    pub value: String,
+}
+
+/// Phase durations in milliseconds. Connection phases are absent when no origin
+/// was contacted or an existing connection was reused.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowTimings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_ms: Option<f64>,
+    /// Until the response headers were ready for the client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttfb_ms: Option<f64>,
+    /// Until the last response byte was handed to the client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_ms: Option<f64>,
+}
+
+/// How a response relates to the cache.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CacheOutcome {
+    Hit,
+    Miss,
+    Bypass,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheDecision {
+    pub outcome: CacheOutcome,
+    /// Why, e.g. the rule that bypassed the cache.
+    pub reason: String,
+}
+
+/// A request or response body captured for the dashboard.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedBody {
+    /// Bytes on the wire, including any beyond `flows.max_body_bytes`.
+    pub size: u64,
+    /// Only the first `flows.max_body_bytes` were kept.
+    pub truncated: bool,
+    /// Decoded content; absent for content that could not be decoded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// `base64` when `text` holds binary content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MockSummary {
+    pub rule: String,
+    /// The response is an injected `failure_status` rather than the configured one.
+    pub failure_injected: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum PassthroughReason {
+    /// A rule with the `tunnel` action matched the host.
+    Rule { name: String },
+    /// The host was learned from repeated handshake failures.
+    Learned,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSocketSummary {
+    pub frames_from_client: u64,
+    pub frames_from_server: u64,
+    pub bytes_from_client: u64,
+    pub bytes_from_server: u64,
+    pub closed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum FrameDirection {
+    ClientToServer,
+    ServerToClient,
+}
+
+/// A single WebSocket frame observed in a tunnel, published when frame inspection is on.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSocketFrame {
+    pub flow_id: String,
+    pub direction: FrameDirection,
+    pub opcode: String,
+    pub fin: bool,
+    pub length: u64,
+    /// Leading payload bytes of text and close frames, lossily decoded as UTF-8.
+    pub preview: Option<String>,
 }
\ No newline at end of file
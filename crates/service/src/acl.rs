@@ -0,0 +1,123 @@
+// File: crates/service/src/acl.rs
+
+use anyhow::{Context, Result};
+use ipnet::IpNet;
+use sentiric_core::{AccessList, AccessRejections};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::warn;
+
+static PROXY_REJECTIONS: AtomicU64 = AtomicU64::new(0);
+static DNS_REJECTIONS: AtomicU64 = AtomicU64::new(0);
+static MANAGEMENT_REJECTIONS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy)]
+pub enum Listener {
+    Proxy,
+    Dns,
+    Management,
+}
+
+impl Listener {
+    fn counter(self) -> &'static AtomicU64 {
+        match self {
+            Listener::Proxy => &PROXY_REJECTIONS,
+            Listener::Dns => &DNS_REJECTIONS,
+            Listener::Management => &MANAGEMENT_REJECTIONS,
+        }
+    }
+}
+
+/// Parsed `AccessList` bound to the listener whose rejections it counts.
+#[derive(Debug, Clone)]
+pub struct AccessControl {
+    listener: Listener,
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+impl AccessControl {
+    pub fn new(listener: Listener, list: &AccessList) -> Result<Self> {
+        Ok(Self {
+            listener,
+            allow: parse_networks(&list.allow).with_context(|| format!("Invalid {:?} allow list", listener))?,
+            deny: parse_networks(&list.deny).with_context(|| format!("Invalid {:?} deny list", listener))?,
+        })
+    }
+
+    /// Returns whether `ip` may use the listener, logging and counting rejections.
+    pub fn check(&self, ip: IpAddr) -> bool {
+        // IPv4 istemciler çift yığınlı soketlerde ::ffff:a.b.c.d olarak görünür
+        let ip = ip.to_canonical();
+        let denied = self.deny.iter().any(|net| net.contains(&ip));
+        let allowed = self.allow.is_empty() || self.allow.iter().any(|net| net.contains(&ip));
+        if denied || !allowed {
+            self.listener.counter().fetch_add(1, Ordering::Relaxed);
+            warn!("Rejected {:?} client {} by access list", self.listener, ip);
+            return false;
+        }
+        true
+    }
+}
+
+pub fn rejections() -> AccessRejections {
+    AccessRejections {
+        proxy: PROXY_REJECTIONS.load(Ordering::Relaxed),
+        dns: DNS_REJECTIONS.load(Ordering::Relaxed),
+        management: MANAGEMENT_REJECTIONS.load(Ordering::Relaxed),
+    }
+}
+
+pub(crate) fn parse_networks(entries: &[String]) -> Result<Vec<IpNet>> {
+    entries
+        .iter()
+        .map(|entry| {
+            entry
+                .parse::<IpNet>()
+                .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                .with_context(|| format!("'{}' is not an IP address or CIDR", entry))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn acl(allow: &[&str], deny: &[&str]) -> AccessControl {
+        let list = AccessList {
+            allow: allow.iter().map(|s| s.to_string()).collect(),
+            deny: deny.iter().map(|s| s.to_string()).collect(),
+        };
+        AccessControl::new(Listener::Dns, &list).unwrap()
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let acl = acl(&["10.0.0.0/8"], &["10.1.2.3"]);
+        assert!(acl.check("10.9.9.9".parse().unwrap()));
+        assert!(!acl.check("10.1.2.3".parse().unwrap()));
+        assert!(!acl.check("192.168.1.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn empty_allow_admits_everyone_not_denied() {
+        let acl = acl(&[], &["192.0.2.0/24"]);
+        assert!(acl.check("198.51.100.7".parse().unwrap()));
+        assert!(!acl.check("192.0.2.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn ipv4_mapped_peers_match_ipv4_rules() {
+        let acl = acl(&["127.0.0.0/8"], &["127.0.0.2"]);
+        assert!(acl.check("::ffff:127.0.0.1".parse().unwrap()));
+        assert!(!acl.check("::ffff:127.0.0.2".parse().unwrap()));
+        assert!(!acl.check("::ffff:10.0.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        assert!(parse_networks(&["10.0.0.0/33".to_string()]).is_err());
+        assert!(parse_networks(&["localhost".to_string()]).is_err());
+    }
+}
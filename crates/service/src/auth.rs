@@ -0,0 +1,181 @@
+// File: crates/service/src/auth.rs
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hyper::header::{HeaderName, HeaderValue, AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, SET_COOKIE};
+use sentiric_core::ProxyAuth;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tracing::{info, warn};
+
+static STORE: OnceLock<UserStore> = OnceLock::new();
+
+/// Users for Basic `Proxy-Authorization` and SOCKS5 username/password logins.
+struct UserStore {
+    realm: String,
+    users: HashMap<String, String>,
+    // bcrypt kasıtlı olarak yavaş; doğrulanmış kimlik bilgilerini özetleriyle hatırlıyoruz
+    verified: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+/// Loads the users file when proxy authentication is enabled.
+pub fn init(settings: &ProxyAuth) -> Result<()> {
+    if !settings.enabled {
+        return Ok(());
+    }
+    let content = std::fs::read_to_string(&settings.users_file)
+        .with_context(|| format!("Failed to read proxy users file '{}'", settings.users_file))?;
+
+    let mut users = HashMap::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (user, hash) = line
+            .split_once(':')
+            .with_context(|| format!("{}:{}: expected 'user:password'", settings.users_file, index + 1))?;
+        if !is_hashed(hash) {
+            warn!("Password for proxy user '{}' is stored in plain text.", user);
+        }
+        users.insert(user.to_string(), hash.to_string());
+    }
+    info!("Proxy authentication enabled with {} user(s).", users.len());
+
+    let store = UserStore { realm: settings.realm.clone(), users, verified: Mutex::new(HashMap::new()) };
+    STORE.set(store).map_err(|_| anyhow::anyhow!("Proxy users already initialized"))?;
+    Ok(())
+}
+
+pub fn is_enabled() -> bool {
+    STORE.get().is_some()
+}
+
+pub fn realm() -> &'static str {
+    STORE.get().map(|store| store.realm.as_str()).unwrap_or_default()
+}
+
+/// Returns the user named by a `Basic` `Proxy-Authorization` header if its password is valid.
+pub fn authenticate_header(header: Option<&HeaderValue>) -> Option<String> {
+    let (user, password) = basic_credentials(header?)?;
+    if verify(&user, &password) {
+        Some(user)
+    } else {
+        warn!("Rejected proxy credentials for user '{}'.", user);
+        None
+    }
+}
+
+/// Decodes a `Basic` header value into its user and password.
+fn basic_credentials(header: &HeaderValue) -> Option<(String, String)> {
+    let (scheme, encoded) = header.to_str().ok()?.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (user, password) = decoded.split_once(':')?;
+    Some((user.to_string(), password.to_string()))
+}
+
+pub fn verify(user: &str, password: &str) -> bool {
+    STORE.get().is_some_and(|store| store.verify(user, password))
+}
+
+impl UserStore {
+    fn verify(&self, user: &str, password: &str) -> bool {
+        let Some(hash) = self.users.get(user) else { return false };
+
+        let fingerprint = Sha1::digest(format!("{}:{}", user, password)).to_vec();
+        if self.verified.lock().unwrap().get(user).is_some_and(|known| constant_time_eq(known, &fingerprint)) {
+            return true;
+        }
+        let valid = if hash.starts_with("$2") {
+            bcrypt::verify(password, hash).unwrap_or(false)
+        } else if let Some(digest) = hash.strip_prefix("{SHA}") {
+            constant_time_eq(STANDARD.encode(Sha1::digest(password)).as_bytes(), digest.as_bytes())
+        } else {
+            // Özetleri karşılaştırmak parola uzunluğunu da sızdırmaz
+            constant_time_eq(&Sha1::digest(hash), &Sha1::digest(password))
+        };
+        if valid {
+            self.verified.lock().unwrap().insert(user.to_string(), fingerprint);
+        }
+        valid
+    }
+}
+
+fn is_hashed(hash: &str) -> bool {
+    hash.starts_with("$2") || hash.starts_with("{SHA}")
+}
+
+/// Compares without returning early, so response times do not reveal how many
+/// leading bytes matched.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Stands in for credential header values in flows and HAR recordings.
+pub const REDACTED: &str = "[redacted]";
+
+/// Headers whose values are credentials and must not leave the proxy in recordings.
+const CREDENTIAL_HEADERS: [HeaderName; 4] = [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE, SET_COOKIE];
+
+/// The value of a header as recorders should show it, with credentials masked.
+pub fn redacted_value(name: &HeaderName, value: &HeaderValue) -> String {
+    if CREDENTIAL_HEADERS.contains(name) {
+        return REDACTED.to_string();
+    }
+    String::from_utf8_lossy(value.as_bytes()).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(users: &[(&str, &str)]) -> UserStore {
+        UserStore {
+            realm: "test".to_string(),
+            users: users.iter().map(|(u, h)| (u.to_string(), h.to_string())).collect(),
+            verified: Mutex::new(HashMap::new()),
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_password_and_unknown_user() {
+        let sha = format!("{{SHA}}{}", STANDARD.encode(Sha1::digest("secret")));
+        let store = store(&[("alice", "secret"), ("bob", sha.as_str())]);
+        assert!(store.verify("alice", "secret"));
+        assert!(!store.verify("alice", "Secret"));
+        assert!(store.verify("bob", "secret"));
+        assert!(!store.verify("bob", "wrong"));
+        assert!(!store.verify("mallory", "secret"));
+    }
+
+    #[test]
+    fn parses_only_well_formed_basic_headers() {
+        let header = |value: &str| HeaderValue::from_str(value).unwrap();
+        let encoded = STANDARD.encode("alice:pa:ss");
+        assert_eq!(
+            basic_credentials(&header(&format!("basic {}", encoded))),
+            Some(("alice".to_string(), "pa:ss".to_string()))
+        );
+        assert_eq!(basic_credentials(&header(&format!("Bearer {}", encoded))), None);
+        assert_eq!(basic_credentials(&header("Basic")), None);
+        assert_eq!(basic_credentials(&header("Basic !!!not-base64")), None);
+        assert_eq!(basic_credentials(&header(&format!("Basic {}", STANDARD.encode("no-colon")))), None);
+    }
+
+    #[test]
+    fn verified_credentials_skip_the_hash_check() {
+        let hash = bcrypt::hash("secret", 4).unwrap();
+        let mut store = store(&[("alice", hash.as_str())]);
+        assert!(store.verify("alice", "secret"));
+        assert!(store.verified.lock().unwrap().contains_key("alice"));
+
+        // Önbellek isabet ederse bozuk özet hiç değerlendirilmez
+        store.users.insert("alice".to_string(), "$2b$04$broken".to_string());
+        assert!(store.verify("alice", "secret"));
+        assert!(!store.verify("alice", "other"));
+    }
+}
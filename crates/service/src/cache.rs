@@ -1,24 +1,97 @@
+use crate::shutdown;
 use anyhow::{Context, Result};
-use sentiric_core::{Stats, CacheEntryInfo};
+use sentiric_core::{Stats, CacheEntryInfo, UpstreamStats};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
-use tokio::{fs, io::AsyncWriteExt};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use tokio::{fs, io::{AsyncReadExt, AsyncWriteExt}};
 use tracing::{debug, info, instrument, warn};
-use hyper::body::{Body, Sender};
+use hyper::body::{Body, Bytes, Sender};
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 
+/// Bir istemciye tek seferde gönderilen, büyümekte olan dosyadan okunan maksimum parça boyutu.
+const TAIL_CHUNK_SIZE: u64 = 64 * 1024;
+
 #[derive(Serialize, Deserialize, Debug)]
 struct CacheMetadata {
     key: String,
     content_encoding: Option<String>,
     content_type: Option<String>,
+    // Eski meta dosyalarında bu alan yok.
+    #[serde(default)]
+    upstream: Option<UpstreamSample>,
+    /// Unix time at which the origin response was received.
+    #[serde(default)]
+    stored_at: Option<u64>,
+}
+
+/// Response headers restored for a cache hit.
+pub struct StoredHeaders {
+    pub content_encoding: Option<String>,
+    pub content_type: Option<String>,
+    /// Time since the entry was fetched from the origin.
+    pub age: Option<Duration>,
+}
+
+/// How long the origin took to deliver an entry when it was filled.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct UpstreamSample {
+    latency_ms: u64,
+    throughput_bytes_per_sec: u64,
+}
+
+impl UpstreamSample {
+    /// Estimated time to fetch `bytes` of this entry from the origin.
+    fn estimate_ms(&self, bytes: u64) -> u64 {
+        self.latency_ms + bytes.saturating_mul(1000) / self.throughput_bytes_per_sec.max(1)
+    }
+}
+
+/// Running totals of cache fills per domain.
+#[derive(Default)]
+struct UpstreamTotals {
+    fills: u64,
+    bytes_downloaded: u64,
+    latency_ms: u64,
+    transfer_ms: u64,
+}
+
+impl UpstreamTotals {
+    fn sample(&self) -> Option<UpstreamSample> {
+        (self.fills > 0).then(|| UpstreamSample {
+            latency_ms: self.latency_ms / self.fills,
+            throughput_bytes_per_sec: self.bytes_downloaded.saturating_mul(1000) / self.transfer_ms.max(1),
+        })
+    }
+}
+
+fn domain_of(key: &str) -> Option<String> {
+    url::Url::parse(key).ok()?.host_str().map(str::to_string)
 }
 
+/// Progress of a cache fill that is still being written to disk.
+#[derive(Clone, Copy, Debug)]
+enum FillState {
+    /// The writer has flushed this many bytes to the `.part` file so far.
+    Writing(u64),
+    /// The fill finished; the file holds exactly this many bytes.
+    Complete(u64),
+    /// The upstream stream or the disk write failed; the partial file is discarded.
+    Failed,
+}
+
+type InFlightFills = Arc<Mutex<HashMap<String, watch::Receiver<FillState>>>>;
+
 pub struct CacheManager {
     disk_path: PathBuf,
     pub stats: Arc<CacheStatsInternal>,
+    // Diske yazılmakta olan girdiler. Geç gelen istemciler buradan dosyanın
+    // büyümesini takip ederek aynı indirmeyi paylaşır.
+    in_flight: InFlightFills,
 }
 
 #[derive(Default)]
@@ -28,16 +101,49 @@ pub struct CacheStatsInternal {
     pub disk_items: AtomicU64,
     pub total_disk_size_bytes: AtomicU64,
     pub bytes_saved: AtomicU64,
+    pub time_saved_ms: AtomicU64,
+    upstream: Mutex<HashMap<String, UpstreamTotals>>,
+}
+
+impl CacheStatsInternal {
+    fn record_fill(&self, key: &str, bytes: u64, latency: Duration, transfer: Duration) {
+        let Some(domain) = domain_of(key) else { return };
+        let mut upstream = self.upstream.lock().unwrap();
+        let totals = upstream.entry(domain).or_default();
+        totals.fills += 1;
+        totals.bytes_downloaded += bytes;
+        totals.latency_ms += latency.as_millis() as u64;
+        totals.transfer_ms += transfer.as_millis() as u64;
+    }
+
+    /// Accounts for a hit once its body has been delivered (fully or partially).
+    ///
+    /// The time saved is the upstream estimate for the delivered bytes minus the
+    /// time it actually took to serve them locally. Entries filled before their
+    /// measurements were recorded fall back to the domain average.
+    fn record_hit(&self, key: &str, sample: Option<UpstreamSample>, delivered: u64, elapsed: Duration) {
+        self.bytes_saved.fetch_add(delivered, Ordering::Relaxed);
+        let sample = sample.or_else(|| {
+            let domain = domain_of(key)?;
+            self.upstream.lock().unwrap().get(&domain)?.sample()
+        });
+        if let Some(sample) = sample {
+            let saved = sample.estimate_ms(delivered).saturating_sub(elapsed.as_millis() as u64);
+            self.time_saved_ms.fetch_add(saved, Ordering::Relaxed);
+        }
+    }
 }
 
 impl CacheManager {
     pub fn new(path: &str) -> Result<Self> {
         let disk_path = Path::new(path).to_path_buf();
         std::fs::create_dir_all(&disk_path).context("Failed to create cache directory")?;
+        Self::remove_stale_fills(&disk_path)?;
         info!("Disk cache enabled at: {:?}", disk_path);
         Ok(Self {
             disk_path,
             stats: Arc::new(CacheStatsInternal::default()),
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
@@ -51,9 +157,31 @@ impl CacheManager {
             disk_items: self.stats.disk_items.load(Ordering::Relaxed),
             total_disk_size_bytes: self.stats.total_disk_size_bytes.load(Ordering::Relaxed),
             bytes_saved: self.stats.bytes_saved.load(Ordering::Relaxed),
+            time_saved_ms: self.stats.time_saved_ms.load(Ordering::Relaxed),
+            upstream: self.upstream_stats(),
+            rejected_clients: crate::acl::rejections(),
         }
     }
 
+    fn upstream_stats(&self) -> Vec<UpstreamStats> {
+        let upstream = self.stats.upstream.lock().unwrap();
+        let mut list: Vec<UpstreamStats> = upstream
+            .iter()
+            .filter_map(|(domain, totals)| {
+                let sample = totals.sample()?;
+                Some(UpstreamStats {
+                    domain: domain.clone(),
+                    fills: totals.fills,
+                    bytes_downloaded: totals.bytes_downloaded,
+                    avg_latency_ms: sample.latency_ms,
+                    avg_throughput_bytes_per_sec: sample.throughput_bytes_per_sec,
+                })
+            })
+            .collect();
+        list.sort_by(|a, b| a.domain.cmp(&b.domain));
+        list
+    }
+
     fn key_to_path(&self, key: &str) -> PathBuf {
         let hash = format!("{:x}", md5::compute(key));
         self.disk_path.join(hash)
@@ -61,97 +189,291 @@ impl CacheManager {
 
     #[instrument(skip(self))]
     pub async fn get(&self, key: &str) -> Option<Body> {
+        if let Some((file, state_rx)) = self.open_in_flight(key) {
+            debug!("CACHE HIT (in-flight): {}", key);
+            self.stats.hits.fetch_add(1, Ordering::Relaxed);
+            return Some(self.deliver(key, file, state_rx, None));
+        }
+
         let path = self.key_to_path(key);
         if path.exists() {
+            let file = fs::File::open(path).await.ok()?;
+            let len = file.metadata().await.ok()?.len();
             debug!("CACHE HIT (disk): {}", key);
             self.stats.hits.fetch_add(1, Ordering::Relaxed);
-            
-            // Dosya boyutunu al ve bytes_saved'a ekle
-            if let Ok(metadata) = fs::metadata(&path).await {
-                self.stats.bytes_saved.fetch_add(metadata.len(), Ordering::Relaxed);
-            }
 
-            let file = fs::File::open(path).await.ok()?;
-            let stream = tokio_util::io::ReaderStream::new(file);
-            return Some(Body::wrap_stream(stream));
+            let sample = self.read_metadata(key).await.and_then(|m| m.upstream);
+            let (_, state_rx) = watch::channel(FillState::Complete(len));
+            return Some(self.deliver(key, file, state_rx, sample));
         }
         debug!("CACHE MISS: {}", key);
         self.stats.misses.fetch_add(1, Ordering::Relaxed);
         None
     }
 
-    // Header'ları almak için yeni fonksiyon
-    pub async fn get_headers(&self, key: &str) -> Option<(String, String)> {
-        let meta_path = self.key_to_path(key).with_extension("meta");
-        if let Ok(meta_content) = fs::read_to_string(meta_path).await {
-            if let Ok(metadata) = serde_json::from_str::<CacheMetadata>(&meta_content) {
-                return Some((
-                    metadata.content_encoding.unwrap_or_default(),
-                    metadata.content_type.unwrap_or_default()
-                ));
+    /// Opens the `.part` file of an in-progress fill for `key`, if there is one.
+    ///
+    /// The file is opened while the in-flight lock is held, so the writer cannot
+    /// rename it away between the lookup and the open.
+    fn open_in_flight(&self, key: &str) -> Option<(fs::File, watch::Receiver<FillState>)> {
+        let in_flight = self.in_flight.lock().unwrap();
+        let state_rx = in_flight.get(key)?.clone();
+        let part_path = self.key_to_path(key).with_extension("part");
+        match std::fs::File::open(&part_path) {
+            Ok(file) => Some((fs::File::from_std(file), state_rx)),
+            Err(e) => {
+                warn!("In-flight cache file for {} could not be opened: {}", key, e);
+                None
+            }
+        }
+    }
+
+    /// Streams a cache file to a new client body and accounts for the bytes it actually delivered.
+    fn deliver(
+        &self,
+        key: &str,
+        file: fs::File,
+        state_rx: watch::Receiver<FillState>,
+        sample: Option<UpstreamSample>,
+    ) -> Body {
+        let (tx, body) = Body::channel();
+        let stats = self.stats.clone();
+        let key = key.to_string();
+        tokio::spawn(async move {
+            let started = Instant::now();
+            let mut delivered = 0;
+            if let Err(e) = Self::tail_to_client(file, state_rx, tx, &mut delivered).await {
+                debug!("Stopped serving cache entry {}: {}", key, e);
+            }
+            stats.record_hit(&key, sample, delivered, started.elapsed());
+        });
+        body
+    }
+
+    /// Streams a (possibly still growing) cache file to a client until the writer
+    /// reports completion. `delivered` counts the bytes handed to the client.
+    async fn tail_to_client(
+        mut file: fs::File,
+        mut state_rx: watch::Receiver<FillState>,
+        mut tx: Sender,
+        delivered: &mut u64,
+    ) -> Result<()> {
+        loop {
+            let state = *state_rx.borrow_and_update();
+            let available = match state {
+                FillState::Writing(written) => written,
+                FillState::Complete(total) => total,
+                FillState::Failed => {
+                    tx.abort();
+                    anyhow::bail!("cache fill failed upstream");
+                }
+            };
+
+            while *delivered < available {
+                let want = (available - *delivered).min(TAIL_CHUNK_SIZE) as usize;
+                let mut buf = vec![0u8; want];
+                let n = file.read(&mut buf).await.context("Failed to read in-flight cache file")?;
+                if n == 0 {
+                    tx.abort();
+                    anyhow::bail!("cache file is shorter than reported");
+                }
+                buf.truncate(n);
+                if tx.send_data(Bytes::from(buf)).await.is_err() {
+                    anyhow::bail!("client disconnected");
+                }
+                *delivered += n as u64;
+            }
+
+            if let FillState::Complete(total) = state {
+                if *delivered >= total {
+                    return Ok(());
+                }
+            } else if state_rx.changed().await.is_err() {
+                // Yazıcı görev bir sonuç bildirmeden sonlandı; akışı yarıda bırakma.
+                tx.abort();
+                anyhow::bail!("cache writer went away");
             }
         }
-        None
     }
 
+    async fn read_metadata(&self, key: &str) -> Option<CacheMetadata> {
+        let meta_path = self.key_to_path(key).with_extension("meta");
+        let meta_content = fs::read_to_string(meta_path).await.ok()?;
+        serde_json::from_str::<CacheMetadata>(&meta_content).ok()
+    }
+
+    // Header'ları almak için yeni fonksiyon
+    pub async fn get_headers(&self, key: &str) -> Option<StoredHeaders> {
+        let metadata = self.read_metadata(key).await?;
+        let stored_at = match metadata.stored_at {
+            Some(secs) => Some(UNIX_EPOCH + Duration::from_secs(secs)),
+            // Eski meta dosyalarında zaman yok; dosyanın yazılma zamanı yeterince yakın
+            None => fs::metadata(self.key_to_path(key)).await.and_then(|m| m.modified()).ok(),
+        };
+        Some(StoredHeaders {
+            content_encoding: metadata.content_encoding,
+            content_type: metadata.content_type,
+            age: stored_at.map(|t| SystemTime::now().duration_since(t).unwrap_or_default()),
+        })
+    }
+
+    /// Streams an upstream response to the client while filling the cache.
+    ///
+    /// `upstream_latency` is the time the origin took to return response headers;
+    /// together with the body transfer time it is recorded for time-saved estimates.
     #[instrument(skip(self, body_stream))]
-    pub async fn put_stream(&self, key: String, body_stream: Body, content_encoding: Option<String>, content_type: Option<String>) -> Result<Body> {
-        let (tx, body_for_client) = Body::channel();
+    pub async fn put_stream(&self, key: String, body_stream: Body, content_encoding: Option<String>, content_type: Option<String>, upstream_latency: Duration) -> Result<Body> {
         let path = self.key_to_path(&key);
         let stats_clone = self.stats.clone();
+
+        // Aynı anahtar zaten dolduruluyorsa ikinci bir yazıcı başlatma;
+        // bu yanıtı önbelleğe dokunmadan istemciye aktar.
+        let state_tx = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            if in_flight.contains_key(&key) {
+                debug!("Fill already in progress for {}, passing response through", key);
+                return Ok(body_stream);
+            }
+            let (state_tx, state_rx) = watch::channel(FillState::Writing(0));
+            in_flight.insert(key.clone(), state_rx);
+            state_tx
+        };
+
+        let (tx, body_for_client) = Body::channel();
         
         // Metadata'yı kaydet
-        let metadata = CacheMetadata {
+        let mut metadata = CacheMetadata {
             key: key.clone(),
             content_encoding,
             content_type,
+            upstream: None,
+            stored_at: SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs()),
         };
 
         let meta_path = path.with_extension("meta");
-        if let Ok(meta_json) = serde_json::to_string(&metadata) {
-            if let Err(e) = fs::write(&meta_path, meta_json).await {
-                warn!("Failed to write meta file for cache key {}: {}", key, e);
-            }
-        }
+        Self::write_metadata(&meta_path, &metadata).await;
 
+        let in_flight = self.in_flight.clone();
         tokio::spawn(async move {
-            if let Err(e) =
-                Self::stream_to_disk_and_client(body_stream, tx, path, key, stats_clone).await
-            {
-                warn!("Failed to cache response: {}", e);
+            let _guard = shutdown::track();
+            let part_path = path.with_extension("part");
+            let transfer_started = Instant::now();
+            let result = Self::stream_to_disk_and_client(body_stream, tx, &part_path, &state_tx).await;
+
+            if let Ok(total_bytes) = result {
+                let transfer = transfer_started.elapsed();
+                metadata.upstream = Some(UpstreamSample {
+                    latency_ms: upstream_latency.as_millis() as u64,
+                    throughput_bytes_per_sec: total_bytes.saturating_mul(1000) / (transfer.as_millis() as u64).max(1),
+                });
+                Self::write_metadata(&meta_path, &metadata).await;
+                stats_clone.record_fill(&key, total_bytes, upstream_latency, transfer);
             }
+
+            // Sonucu yayınla ve dosyayı kilit altında yerine taşı; böylece `get`
+            // hiçbir zaman ne `.part` ne de nihai dosyayı bulamadığı bir ara duruma düşmez.
+            let mut in_flight = in_flight.lock().unwrap();
+            let outcome = result.and_then(|total_bytes| {
+                std::fs::rename(&part_path, &path).context("Failed to finalize cache file")?;
+                Ok(total_bytes)
+            });
+            match outcome {
+                Ok(total_bytes) => {
+                    state_tx.send_replace(FillState::Complete(total_bytes));
+                    stats_clone.disk_items.fetch_add(1, Ordering::Relaxed);
+                    stats_clone.total_disk_size_bytes.fetch_add(total_bytes, Ordering::Relaxed);
+                    info!("CACHE PUT: {} ({} bytes)", key, total_bytes);
+                }
+                Err(e) => {
+                    state_tx.send_replace(FillState::Failed);
+                    let _ = std::fs::remove_file(&part_path);
+                    let _ = std::fs::remove_file(&meta_path);
+                    warn!("Failed to cache response: {}", e);
+                }
+            }
+            in_flight.remove(&key);
         });
-        
+
         Ok(body_for_client)
     }
-    
+
+    async fn write_metadata(meta_path: &Path, metadata: &CacheMetadata) {
+        if let Ok(meta_json) = serde_json::to_string(metadata) {
+            if let Err(e) = fs::write(meta_path, meta_json).await {
+                warn!("Failed to write meta file for cache key {}: {}", metadata.key, e);
+            }
+        }
+    }
+
     async fn stream_to_disk_and_client(
         mut body_stream: Body,
         mut tx: Sender,
-        path: PathBuf,
-        key: String,
-        stats: Arc<CacheStatsInternal>,
-    ) -> Result<()> {
-        let mut file = fs::File::create(&path).await.context("Failed to create cache file")?;
+        part_path: &Path,
+        state_tx: &watch::Sender<FillState>,
+    ) -> Result<u64> {
+        let result = Self::copy_to_disk_and_client(&mut body_stream, &mut tx, part_path, state_tx).await;
+        if result.is_err() {
+            // İstemcinin yanıtı eksiksiz sanmaması için gövdeyi hatayla sonlandır.
+            tx.abort();
+        }
+        result
+    }
+
+    async fn copy_to_disk_and_client(
+        body_stream: &mut Body,
+        tx: &mut Sender,
+        part_path: &Path,
+        state_tx: &watch::Sender<FillState>,
+    ) -> Result<u64> {
+        let mut file = fs::File::create(part_path).await.context("Failed to create cache file")?;
         let mut total_bytes = 0;
         while let Some(chunk_result) = body_stream.next().await {
             let chunk = chunk_result.context("Error reading response stream")?;
             file.write_all(&chunk).await.context("Failed to write to cache file")?;
+            // Takip eden okuyucuların veriyi görebilmesi için her parçadan sonra diske boşalt.
+            file.flush().await.context("Failed to flush cache file")?;
             total_bytes += chunk.len() as u64;
+            state_tx.send_replace(FillState::Writing(total_bytes));
             let _ = tx.send_data(chunk).await;
         }
-        stats.disk_items.fetch_add(1, Ordering::Relaxed);
-        stats.total_disk_size_bytes.fetch_add(total_bytes, Ordering::Relaxed);
-        info!("CACHE PUT: {} ({} bytes)", key, total_bytes);
+        Ok(total_bytes)
+    }
+
+    /// Deletes fills that were interrupted by a previous shutdown or crash.
+    fn remove_stale_fills(disk_path: &Path) -> Result<()> {
+        for entry in std::fs::read_dir(disk_path)? {
+            let part_path = entry?.path();
+            if part_path.extension().is_none_or(|ext| ext != "part") {
+                continue;
+            }
+            std::fs::remove_file(&part_path)?;
+            // Tamamlanmış bir önceki kopya varsa onun meta dosyasına dokunma
+            if !part_path.with_extension("").exists() {
+                let _ = std::fs::remove_file(part_path.with_extension("meta"));
+            }
+            info!("Removed interrupted cache fill {:?}", part_path);
+        }
         Ok(())
     }
 
+    /// Drops fills that are still running so no partial entry survives shutdown.
+    /// Their writers notice the missing `.part` file when they try to finalize it.
+    pub fn discard_in_flight(&self) {
+        let in_flight = self.in_flight.lock().unwrap();
+        for key in in_flight.keys() {
+            let path = self.key_to_path(key);
+            let _ = std::fs::remove_file(path.with_extension("part"));
+            let _ = std::fs::remove_file(path.with_extension("meta"));
+            warn!("Discarded unfinished cache fill for {}", key);
+        }
+    }
+
     pub async fn list_entries(&self) -> Result<Vec<CacheEntryInfo>> {
         let mut entries = Vec::new();
         let mut read_dir = fs::read_dir(&self.disk_path).await?;
         while let Some(entry) = read_dir.next_entry().await? {
             let path = entry.path();
-            if path.extension().is_some() && path.extension().unwrap() == "meta" { continue; }
+            if path.extension().is_some_and(|ext| ext == "meta" || ext == "part") { continue; }
             if path.is_file() {
                 let metadata = entry.metadata().await?;
                 let meta_path = path.with_extension("meta");
@@ -183,7 +505,65 @@ impl CacheManager {
         self.stats.disk_items.store(0, Ordering::Relaxed);
         self.stats.total_disk_size_bytes.store(0, Ordering::Relaxed);
         self.stats.bytes_saved.store(0, Ordering::Relaxed);
+        self.stats.time_saved_ms.store(0, Ordering::Relaxed);
+        self.stats.upstream.lock().unwrap().clear();
         info!("Cache cleared successfully.");
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache() -> (CacheManager, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("sentiric-cache-test-{}", uuid::Uuid::new_v4()));
+        (CacheManager::new(dir.to_str().unwrap()).unwrap(), dir)
+    }
+
+    /// Starts a fill and waits until its first chunk is on disk, so `get` tails it.
+    async fn start_fill(cache: &CacheManager, key: &str) -> (Sender, Body) {
+        let (mut upstream, body) = Body::channel();
+        let mut client = cache.put_stream(key.to_string(), body, None, None, Duration::from_millis(5)).await.unwrap();
+        upstream.send_data(Bytes::from_static(b"first ")).await.unwrap();
+        assert_eq!(client.next().await.unwrap().unwrap(), Bytes::from_static(b"first "));
+        (upstream, client)
+    }
+
+    #[tokio::test]
+    async fn tailer_follows_fill_to_complete() {
+        let (cache, dir) = temp_cache();
+        let key = "https://example.com:443/big.bin";
+        let (mut upstream, client) = start_fill(&cache, key).await;
+
+        let tailer = cache.get(key).await.expect("in-flight fill is a hit");
+        let feed = async move {
+            upstream.send_data(Bytes::from_static(b"second ")).await.unwrap();
+            upstream.send_data(Bytes::from_static(b"third")).await.unwrap();
+        };
+        // Yazıcı ilk istemci okumadıkça ilerlemez; gövdeleri birlikte tüket
+        let (_, tailed, rest) = tokio::join!(feed, hyper::body::to_bytes(tailer), hyper::body::to_bytes(client));
+        assert_eq!(&tailed.unwrap()[..], b"first second third");
+        assert_eq!(&rest.unwrap()[..], b"second third");
+        assert!(cache.get(key).await.is_some());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn tailer_sees_error_when_fill_fails() {
+        let (cache, dir) = temp_cache();
+        let key = "https://example.com:443/broken.bin";
+        let (mut upstream, client) = start_fill(&cache, key).await;
+
+        let tailer = cache.get(key).await.expect("in-flight fill is a hit");
+        let feed = async move {
+            upstream.send_data(Bytes::from_static(b"partial")).await.unwrap();
+            upstream.abort();
+        };
+        let (_, tailed, rest) = tokio::join!(feed, hyper::body::to_bytes(tailer), hyper::body::to_bytes(client));
+        assert!(tailed.is_err(), "tailer must not see a silently truncated body");
+        assert!(rest.is_err());
+        // Yarım kalan dolum önbellekte kalmamalı
+        assert!(cache.get(key).await.is_none());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -92,12 +92,9 @@ impl CertificateAuthority {
             .with_no_client_auth()
             .with_single_cert(cert_chain, key)?;
         
-        // ======================== NİHAİ DÜZELTME ========================
-        // Sürdürülebilirlik: ALPN protokollerini, HTTP katmanının beklentisiyle
-        // (http1_only) tutarlı hale getiriyoruz. Bu, TLS el sıkışması sırasında
-        // istemciyle en başından doğru protokol üzerinde anlaşmamızı sağlar.
-        config.alpn_protocols = vec![b"http/1.1".to_vec()];
-        // ========================= DÜZELTME BİTİŞİ =========================
+        // ALPN: HTTP/2'yi öncelikli sun; MitM dinleyicisi anlaşılan protokole göre
+        // bağlantıyı h2 veya HTTP/1.1 olarak servis eder (bkz. `proxy::serve_https`).
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
 
         let arc_config = Arc::new(config);
         cache.insert(domain.to_string(), arc_config.clone());
@@ -1,5 +1,5 @@
-use anyhow::{Context, Result};
-use sentiric_core::Settings;
+use anyhow::{bail, Context, Result};
+use sentiric_core::{Action, Settings};
 use std::sync::OnceLock;
 
 static SETTINGS: OnceLock<Settings> = OnceLock::new();
@@ -13,10 +13,33 @@ pub fn init() -> Result<()> {
         .try_deserialize()
         .context("Failed to deserialize configuration")?;
 
+    validate(&settings)?;
     SETTINGS.set(settings).map_err(|_| anyhow::anyhow!("Configuration already initialized"))?;
     Ok(())
 }
 
 pub fn get() -> &'static Settings {
     SETTINGS.get().expect("Configuration is not initialized")
-}
\ No newline at end of file
+}
+
+/// Rejects values that deserialize fine but cannot work at runtime.
+fn validate(settings: &Settings) -> Result<()> {
+    let throttle = &settings.throttle;
+    for (name, rate) in [
+        ("global_bytes_per_sec", throttle.global_bytes_per_sec),
+        ("per_client_bytes_per_sec", throttle.per_client_bytes_per_sec),
+        ("per_domain_bytes_per_sec", throttle.per_domain_bytes_per_sec),
+    ] {
+        if rate == Some(0) {
+            bail!("[throttle] {} must be greater than 0; omit it to disable the limit", name);
+        }
+    }
+    for rule in &settings.rules {
+        match &rule.action {
+            Action::Throttle(0) => bail!("Rule '{}': throttle rate must be greater than 0", rule.name),
+            Action::Mock(mock) => crate::mock::validate(mock).with_context(|| format!("Rule '{}'", rule.name))?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
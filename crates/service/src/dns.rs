@@ -1,8 +1,10 @@
+use crate::acl::{AccessControl, Listener};
+use crate::listen;
+use crate::shutdown;
 use anyhow::Result;
 use async_trait::async_trait;
 use sentiric_core::Settings;
-use std::net::{IpAddr, SocketAddr};
-use tokio::net::UdpSocket;
+use std::net::{IpAddr, Ipv6Addr};
 use tracing::{info, warn};
 
 use trust_dns_server::authority::MessageResponseBuilder;
@@ -13,11 +15,13 @@ use trust_dns_server::server::{Request, RequestHandler, ResponseHandler, Respons
 #[derive(Clone)]
 pub struct DnsHandler {
     response_ip: IpAddr,
+    response_ipv6: Option<Ipv6Addr>,
+    access: AccessControl,
 }
 
 impl DnsHandler {
-    pub fn new(response_ip: IpAddr) -> Self {
-        Self { response_ip }
+    pub fn new(response_ip: IpAddr, response_ipv6: Option<Ipv6Addr>, access: AccessControl) -> Self {
+        Self { response_ip, response_ipv6, access }
     }
 }
 
@@ -29,16 +33,33 @@ impl RequestHandler for DnsHandler {
         mut response_handle: H,
     ) -> ResponseInfo {
         let response_builder = MessageResponseBuilder::from_message_request(request);
-        
+
+        if !self.access.check(request.src().ip()) {
+            let mut header = *request.header();
+            header.set_response_code(ResponseCode::Refused);
+            let response = response_builder.build_no_records(header);
+            return match response_handle.send_response(response).await {
+                Ok(info) => info,
+                Err(e) => {
+                    warn!("error sending DNS response: {}", e);
+                    ResponseInfo::from(header)
+                }
+            };
+        }
+
         let query = request.query();
         info!("Intercepting DNS lookup for: {} ({})", query.name(), query.query_type());
 
-        let answers: Vec<Record> = match (query.query_type(), self.response_ip) {
-            (RecordType::A, IpAddr::V4(ipv4)) => {
+        let ipv6 = self.response_ipv6.or(match self.response_ip {
+            IpAddr::V6(ipv6) => Some(ipv6),
+            IpAddr::V4(_) => None,
+        });
+        let answers: Vec<Record> = match (query.query_type(), self.response_ip, ipv6) {
+            (RecordType::A, IpAddr::V4(ipv4), _) => {
                 let rdata = rdata::A(ipv4);
                 vec![Record::from_rdata(query.name().clone().into(), 60, RData::A(rdata))]
             }
-            (RecordType::AAAA, IpAddr::V6(ipv6)) => {
+            (RecordType::AAAA, _, Some(ipv6)) => {
                 let rdata = rdata::AAAA(ipv6);
                 vec![Record::from_rdata(query.name().clone().into(), 60, RData::AAAA(rdata))]
             }
@@ -49,8 +70,8 @@ impl RequestHandler for DnsHandler {
         header.set_authoritative(true);
         
         let empty_answers: Vec<Record> = vec![]; 
+        // Ad var ama bu tür kayıt yok (NODATA); NXDOMAIN dönersek çift yığınlı istemciler A kaydını da yok sayar
         let response = if answers.is_empty() {
-            header.set_response_code(ResponseCode::NXDomain);
             response_builder.build(header, empty_answers.iter(), None.iter(), None.iter(), None.iter())
         } else {
             response_builder.build(header, answers.iter(), None.iter(), None.iter(), None.iter())
@@ -69,15 +90,25 @@ impl RequestHandler for DnsHandler {
 }
 
 pub async fn run_server(settings: &Settings) -> Result<()> {
-    let addr: SocketAddr = format!("{}:{}", settings.dns.bind_address, settings.dns.port).parse()?;
-    info!("🚀 Smart DNS server listening on udp://{}", addr);
-
-    let handler = DnsHandler::new(settings.dns.response_ip);
+    let access = AccessControl::new(Listener::Dns, &settings.dns.access)?;
+    let handler = DnsHandler::new(settings.dns.response_ip, settings.dns.response_ipv6, access);
 
     let mut server = ServerFuture::new(handler);
-    server.register_socket(UdpSocket::bind(addr).await?);
+    for addr in listen::socket_addrs(&settings.dns.bind_address, settings.dns.port)? {
+        server.register_socket(listen::bind_udp(addr)?);
+        info!("🚀 Smart DNS server listening on udp://{}", addr);
+    }
+
+    let (signal, done) = server.graceful();
+    tokio::pin!(done);
+    tokio::select! {
+        res = &mut done => res?,
+        _ = shutdown::requested() => {
+            signal.shutdown().await;
+            done.await?;
+            info!("DNS server stopped.");
+        }
+    }
 
-    server.block_until_done().await?;
-    
     Ok(())
 }
\ No newline at end of file
@@ -1,27 +1,210 @@
 // File: crates/service/src/downloader.rs
 
-use hyper::{client::HttpConnector, Body, Client, Request, Response, Version};
+use crate::resolver::LoopDetected;
+use crate::upstream::{self, TimedConnector, UpstreamConnector};
+use crate::upstream_tls;
+use futures_util::StreamExt;
+use hyper::body::HttpBody;
+use hyper::{Body, Client, Method, Request, Response, StatusCode, Version};
 use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
 use lazy_static::lazy_static;
-use tracing::{debug, instrument};
+use sentiric_core::UpstreamProtocol;
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::{debug, instrument, warn};
+use trust_dns_resolver::error::ResolveError;
+use wildmatch::WildMatch;
 
-type HttpsClient = Client<HttpsConnector<HttpConnector>>;
+type HttpsClient = Client<TimedConnector<HttpsConnector<UpstreamConnector>>>;
+type BoxError = Box<dyn StdError + Send + Sync>;
 
 lazy_static! {
-    pub static ref HTTP_CLIENT: HttpsClient = {
-        let https = HttpsConnectorBuilder::new()
-            .with_native_roots()
-            .https_or_http()
-            .enable_http1()
-            .build();
-        
-        Client::builder()
-            .http2_only(false)
-            .build(https)
-    };
+    /// ALPN ile anlaşılan protokolü kullanır: origin sunuyorsa HTTP/2, aksi halde HTTP/1.1.
+    pub static ref HTTP_CLIENT: HttpsClient = build_client(UpstreamProtocol::Auto, None);
+
+    /// HTTP/2 ile sorun yaşayan origin'ler için yalnızca HTTP/1.1 sunan istemci.
+    static ref HTTP1_CLIENT: HttpsClient = build_client(UpstreamProtocol::Http1, None);
+
+    /// Yalnızca HTTP/2 konuşan istemci (düz `http://` için prior knowledge / h2c).
+    static ref HTTP2_CLIENT: HttpsClient = build_client(UpstreamProtocol::Http2, None);
+
+    /// mTLS gerektiren origin'ler için (sertifika sırası, protokol) başına ayrı istemciler.
+    static ref CLIENT_CERT_CLIENTS: Mutex<HashMap<(usize, UpstreamProtocol), HttpsClient>> = Mutex::new(HashMap::new());
+
+    static ref HOST_PERMITS: Mutex<HashMap<String, Arc<Semaphore>>> = Mutex::new(HashMap::new());
+}
+
+/// An upstream that stopped responding; connect timeouts surface as `io::ErrorKind::TimedOut`.
+#[derive(Debug)]
+pub enum UpstreamTimeout {
+    /// No response headers arrived in time.
+    FirstByte(Duration),
+    /// The response body stalled between two chunks.
+    Idle(Duration),
+}
+
+impl fmt::Display for UpstreamTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpstreamTimeout::FirstByte(t) => write!(f, "no response headers within {}s", t.as_secs()),
+            UpstreamTimeout::Idle(t) => write!(f, "response body stalled for {}s", t.as_secs()),
+        }
+    }
+}
+
+impl StdError for UpstreamTimeout {}
+
+/// Why an upstream request failed, as far as the client is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    Timeout,
+    /// The origin's certificate was rejected.
+    Certificate,
+    /// The origin's name could not be resolved.
+    Dns,
+    /// The origin refused the connection or could not be reached.
+    Unreachable,
+    /// The origin resolves to one of our own listeners.
+    Loop,
+    Other,
+}
+
+impl FailureKind {
+    /// Status reported to the client: 508 for loops, 504 for timeouts, 502 for everything else.
+    pub fn status(self) -> StatusCode {
+        match self {
+            FailureKind::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            FailureKind::Loop => StatusCode::LOOP_DETECTED,
+            _ => StatusCode::BAD_GATEWAY,
+        }
+    }
+}
+
+/// Every error in the chain of `err`, including errors wrapped in an `io::Error`,
+/// which `source()` skips over.
+pub fn error_chain<'a>(err: &'a (dyn StdError + 'static)) -> Vec<&'a (dyn StdError + 'static)> {
+    let mut chain = Vec::new();
+    let mut next = Some(err);
+    while let Some(e) = next {
+        chain.push(e);
+        next = match e.downcast_ref::<std::io::Error>().and_then(|io| io.get_ref()) {
+            Some(inner) => Some(inner as &(dyn StdError + 'static)),
+            None => e.source(),
+        };
+    }
+    chain
+}
+
+/// Short description of why an upstream request failed, for flow records.
+pub fn failure_reason(err: &(dyn StdError + 'static)) -> String {
+    let chain = error_chain(err);
+    if let Some(timeout) = chain.iter().find_map(|e| e.downcast_ref::<UpstreamTimeout>()) {
+        return timeout.to_string();
+    }
+    if chain.iter().filter_map(|e| e.downcast_ref::<std::io::Error>()).any(|e| e.kind() == std::io::ErrorKind::TimedOut) {
+        return "connect timeout".to_string();
+    }
+    let root = chain.last().map(|e| e.to_string()).unwrap_or_default();
+    if is_connect_error(&chain) {
+        format!("connect failed: {}", root)
+    } else {
+        root
+    }
+}
+
+pub fn failure_kind(err: &(dyn StdError + 'static)) -> FailureKind {
+    let chain = error_chain(err);
+    let timed_out = chain.iter().any(|e| {
+        e.is::<UpstreamTimeout>()
+            || e.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::TimedOut)
+    });
+    if chain.iter().any(|e| e.is::<LoopDetected>()) {
+        FailureKind::Loop
+    } else if timed_out {
+        FailureKind::Timeout
+    } else if upstream_tls::certificate_error(err).is_some() {
+        FailureKind::Certificate
+    } else if chain.iter().any(|e| e.is::<ResolveError>()) {
+        FailureKind::Dns
+    } else if is_connect_error(&chain) {
+        FailureKind::Unreachable
+    } else {
+        FailureKind::Other
+    }
+}
+
+fn is_connect_error(chain: &[&(dyn StdError + 'static)]) -> bool {
+    chain.iter().any(|e| e.downcast_ref::<hyper::Error>().is_some_and(hyper::Error::is_connect))
+}
+
+fn build_client(protocol: UpstreamProtocol, client_cert: Option<usize>) -> HttpsClient {
+    let https = HttpsConnectorBuilder::new()
+        .with_tls_config(upstream_tls::client_config(client_cert))
+        .https_or_http();
+    match protocol {
+        UpstreamProtocol::Auto => client_builder().build(TimedConnector(https.enable_http1().enable_http2().wrap_connector(UpstreamConnector::new()))),
+        UpstreamProtocol::Http1 => client_builder().build(TimedConnector(https.enable_http1().wrap_connector(UpstreamConnector::new()))),
+        UpstreamProtocol::Http2 => client_builder().http2_only(true).build(TimedConnector(https.enable_http2().wrap_connector(UpstreamConnector::new()))),
+    }
+}
+
+/// Picks the shared client for `host`, or a dedicated one if it needs a client certificate.
+fn client_for(host: &str, protocol: UpstreamProtocol) -> HttpsClient {
+    if let Some(index) = upstream_tls::client_cert_for(host) {
+        return CLIENT_CERT_CLIENTS
+            .lock()
+            .unwrap()
+            .entry((index, protocol))
+            .or_insert_with(|| build_client(protocol, Some(index)))
+            .clone();
+    }
+    match protocol {
+        UpstreamProtocol::Auto => HTTP_CLIENT.clone(),
+        UpstreamProtocol::Http1 => HTTP1_CLIENT.clone(),
+        UpstreamProtocol::Http2 => HTTP2_CLIENT.clone(),
+    }
+}
+
+fn client_builder() -> hyper::client::Builder {
+    let settings = &crate::config::get().downloader;
+    let mut builder = Client::builder();
+    builder
+        .pool_max_idle_per_host(settings.pool_max_idle_per_host)
+        .pool_idle_timeout(Duration::from_secs(settings.pool_idle_timeout_secs));
+    builder
+}
+
+/// Returns the protocol configured for `host`, falling back to `Auto`.
+fn protocol_for(host: &str) -> UpstreamProtocol {
+    crate::config::get()
+        .downloader
+        .protocol_overrides
+        .iter()
+        .find(|o| WildMatch::new(&o.domain).matches(host))
+        .map(|o| o.protocol)
+        .unwrap_or_default()
+}
+
+fn host_semaphore(host: &str) -> Arc<Semaphore> {
+    let mut permits = HOST_PERMITS.lock().unwrap();
+    if let Some(semaphore) = permits.get(host) {
+        return semaphore.clone();
+    }
+    // Yalnızca haritanın tuttuğu semaforlarda izin ya da bekleyen yok; boşta olanları at
+    permits.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+    let semaphore = Arc::new(Semaphore::new(crate::config::get().downloader.max_concurrent_per_host.max(1)));
+    permits.insert(host.to_string(), semaphore.clone());
+    semaphore
 }
 
 /// Forwards a request to the internet using the shared HTTP client.
+///
+/// At most `downloader.max_concurrent_per_host` requests run against the same
+/// host at once; a slot is held until the response body has been consumed.
 #[instrument(skip(req))]
 pub async fn forward_request(
     mut req: Request<Body>,
@@ -48,19 +231,30 @@ pub async fn forward_request(
     }
 
     // Host header'ını ayarla
-    let host = req.uri().host().unwrap();
+    let host = req.uri().host().unwrap().to_string();
     let port = req.uri().port_u16().unwrap_or(if req.uri().scheme_str() == Some("https") { 443 } else { 80 });
     let host_header = format!("{}:{}", host, port);
     req.headers_mut().insert(hyper::header::HOST, host_header.parse()?);
+    if let Some(auth) = upstream::proxy_authorization(req.uri()) {
+        req.headers_mut().insert(hyper::header::PROXY_AUTHORIZATION, auth);
+    }
 
-    // HTTP/1.1 kullanmaya zorla
-    *req.version_mut() = Version::HTTP_11;
+    // İstemciyle konuşulan sürüm upstream için anlamsız. HTTP/1.1 olarak işaretlenen
+    // istekler, ALPN h2 seçerse yine de HTTP/2 bağlantısı üzerinden gönderilir.
+    let protocol = protocol_for(&host);
+    *req.version_mut() = match protocol {
+        UpstreamProtocol::Http2 => Version::HTTP_2,
+        UpstreamProtocol::Auto | UpstreamProtocol::Http1 => Version::HTTP_11,
+    };
+    let client = client_for(&host, protocol);
+
+    let permit = host_semaphore(&host).acquire_owned().await?;
 
-    debug!("Forwarding request to: {}", req.uri());
-    let response = HTTP_CLIENT.request(req).await?;
+    debug!("Forwarding request to: {} ({:?})", req.uri(), protocol);
+    let response = send_with_retries(&client, req).await?;
     
     // Response header'larını logla
-    debug!("Received response with status: {}", response.status());
+    debug!("Received response with status: {} ({:?})", response.status(), response.version());
     if let Some(encoding) = response.headers().get("content-encoding") {
         debug!("Response content-encoding: {:?}", encoding);
     }
@@ -68,5 +262,113 @@ pub async fn forward_request(
         debug!("Response content-type: {:?}", content_type);
     }
 
-    Ok(response)
-}
\ No newline at end of file
+    // İzin, gövde tamamen okunana (veya bırakılana) kadar tutulur. Uzun süre veri
+    // gelmezse akış hata ile kesilir; böylece takılan origin istemciyi asılı bırakmaz.
+    let idle = Duration::from_secs(crate::config::get().downloader.idle_timeout_secs);
+    let (parts, body) = response.into_parts();
+    let body = Body::wrap_stream(futures_util::stream::unfold(Some((body, permit)), move |state| async move {
+        let (mut body, permit) = state?;
+        match tokio::time::timeout(idle, body.next()).await {
+            Ok(Some(Ok(chunk))) => Some((Ok(chunk), Some((body, permit)))),
+            Ok(Some(Err(e))) => Some((Err(BoxError::from(e)), None)),
+            Ok(None) => None,
+            Err(_) => Some((Err(UpstreamTimeout::Idle(idle).into()), None)),
+        }
+    }));
+    Ok(Response::from_parts(parts, body))
+}
+
+/// Upper bound for the doubling delay between upstream retries.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Sends `req`, bounding the wait for response headers. Idempotent requests
+/// without a body are retried with exponential backoff when no response arrives.
+async fn send_with_retries(client: &HttpsClient, req: Request<Body>) -> anyhow::Result<Response<Body>> {
+    let settings = &crate::config::get().downloader;
+    let ttfb = Duration::from_secs(settings.ttfb_timeout_secs);
+    let retryable = is_retryable(&req);
+    let template = retryable.then(|| bodiless_copy(&req));
+    let attempts = if retryable { settings.max_retries + 1 } else { 1 };
+    let mut backoff = Duration::from_millis(settings.retry_backoff_ms).min(MAX_RETRY_BACKOFF);
+
+    let mut next = Some(req);
+    let mut attempt = 1;
+    loop {
+        let req = match next.take() {
+            Some(req) => req,
+            None => bodiless_copy(template.as_ref().expect("retries require a template")),
+        };
+        let uri = req.uri().clone();
+        let err = match tokio::time::timeout(ttfb, client.request(req)).await {
+            Ok(Ok(response)) => return Ok(response),
+            Ok(Err(e)) => anyhow::Error::from(e),
+            Err(_) => anyhow::Error::from(UpstreamTimeout::FirstByte(ttfb)),
+        };
+        if attempt >= attempts || is_permanent(err.as_ref()) {
+            return Err(err);
+        }
+        warn!(
+            "Upstream attempt {}/{} for {} failed ({}); retrying in {:?}",
+            attempt, attempts, uri, failure_reason(err.as_ref()), backoff
+        );
+        tokio::time::sleep(backoff).await;
+        backoff = backoff.saturating_mul(2).min(MAX_RETRY_BACKOFF);
+        attempt += 1;
+    }
+}
+
+/// Loops and rejected certificates are configuration problems; retrying cannot help.
+fn is_permanent(err: &(dyn StdError + 'static)) -> bool {
+    matches!(failure_kind(err), FailureKind::Loop | FailureKind::Certificate)
+}
+
+/// Only bodiless idempotent requests can be sent again from a copy of their head.
+pub(crate) fn is_retryable(req: &Request<Body>) -> bool {
+    is_idempotent(req.method()) && req.body().is_end_stream()
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE | Method::PUT | Method::DELETE)
+}
+
+fn bodiless_copy(req: &Request<Body>) -> Request<Body> {
+    let mut copy = Request::new(Body::empty());
+    *copy.method_mut() = req.method().clone();
+    *copy.uri_mut() = req.uri().clone();
+    *copy.version_mut() = req.version();
+    *copy.headers_mut() = req.headers().clone();
+    copy
+}
+
+/// Forwards a WebSocket handshake over HTTP/1.1, keeping the `Upgrade` and
+/// `Connection` headers that `forward_request` strips as hop-by-hop.
+///
+/// Upgraded connections are long-lived, so they do not take a per-host slot.
+#[instrument(skip(req))]
+pub async fn forward_upgrade(mut req: Request<Body>) -> anyhow::Result<Response<Body>> {
+    const STRIPPED_HEADERS: &[&str] = &[
+        "keep-alive", "proxy-authenticate", "proxy-authorization",
+        "te", "trailers", "transfer-encoding", "proxy-connection",
+    ];
+
+    let headers = req.headers_mut();
+    for header in STRIPPED_HEADERS {
+        headers.remove(*header);
+    }
+    headers.insert(hyper::header::CONNECTION, "upgrade".parse()?);
+
+    let host = req.uri().host().unwrap().to_string();
+    let port = req.uri().port_u16().unwrap_or(if req.uri().scheme_str() == Some("https") { 443 } else { 80 });
+    req.headers_mut().insert(hyper::header::HOST, format!("{}:{}", host, port).parse()?);
+    if let Some(auth) = upstream::proxy_authorization(req.uri()) {
+        req.headers_mut().insert(hyper::header::PROXY_AUTHORIZATION, auth);
+    }
+    *req.version_mut() = Version::HTTP_11;
+
+    debug!("Forwarding WebSocket upgrade to: {}", req.uri());
+    let ttfb = Duration::from_secs(crate::config::get().downloader.ttfb_timeout_secs);
+    match tokio::time::timeout(ttfb, client_for(&host, UpstreamProtocol::Http1).request(req)).await {
+        Ok(response) => Ok(response?),
+        Err(_) => Err(UpstreamTimeout::FirstByte(ttfb).into()),
+    }
+}
@@ -0,0 +1,297 @@
+// File: crates/service/src/flows.rs
+
+use crate::auth;
+use crate::management::{EVENT_BROADCASTER, WsEvent};
+use crate::proxy::ClientInfo;
+use crate::upstream::ConnectTimings;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use futures_util::StreamExt;
+use hyper::body::HttpBody;
+use hyper::header::{HeaderMap, CONTENT_ENCODING, CONTENT_TYPE};
+use hyper::{Body, Request, Response};
+use sentiric_core::{CapturedBody, FlowEntry, FlowHeader, Flows};
+use std::io::Read;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+use uuid::Uuid;
+
+/// Why the origin body broke off. Set where the body leaves the origin, since the
+/// client only sees the error of the cache fill in between.
+#[derive(Clone, Default)]
+pub struct BodyError(Arc<OnceLock<String>>);
+
+impl BodyError {
+    pub fn set(&self, reason: String) {
+        let _ = self.0.set(reason);
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct BodyBuffer {
+    pub bytes: Vec<u8>,
+    pub total: u64,
+    pub truncated: bool,
+}
+
+impl BodyBuffer {
+    /// Counts `chunk` and keeps as much of it as fits in `limit`.
+    fn push(&mut self, chunk: &[u8], limit: usize) {
+        self.total += chunk.len() as u64;
+        let room = limit.saturating_sub(self.bytes.len());
+        if chunk.len() > room {
+            self.truncated = true;
+        }
+        self.bytes.extend_from_slice(&chunk[..chunk.len().min(room)]);
+    }
+}
+
+/// Copies the first `limit` bytes of `body` into `buffer` as it streams; `guard`
+/// is dropped once the body has been fully read or abandoned.
+pub(crate) fn tee(body: Body, limit: usize, buffer: Arc<Mutex<BodyBuffer>>, guard: impl Send + 'static) -> Body {
+    Body::wrap_stream(body.map(move |chunk| {
+        let _guard = &guard;
+        if let Ok(bytes) = &chunk {
+            buffer.lock().unwrap().push(bytes, limit);
+        }
+        chunk
+    }))
+}
+
+/// Undoes `content_encoding`; `None` for encodings we cannot decode.
+pub(crate) fn decode(bytes: &[u8], content_encoding: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    let result = match content_encoding.trim().to_ascii_lowercase().as_str() {
+        "" | "identity" => return Some(bytes.to_vec()),
+        "gzip" | "x-gzip" => flate2::read::GzDecoder::new(bytes).read_to_end(&mut decoded),
+        // "deflate" çoğunlukla zlib sarmalıdır, bazı sunucular ham deflate gönderir
+        "deflate" => flate2::read::ZlibDecoder::new(bytes)
+            .read_to_end(&mut decoded)
+            .or_else(|_| {
+                decoded.clear();
+                flate2::read::DeflateDecoder::new(bytes).read_to_end(&mut decoded)
+            }),
+        "br" => brotli::Decompressor::new(bytes, 4096).read_to_end(&mut decoded),
+        _ => return None,
+    };
+    match result {
+        Ok(_) => Some(decoded),
+        Err(e) => {
+            warn!("Failed to decode {} body: {}", content_encoding, e);
+            None
+        }
+    }
+}
+
+pub(crate) fn is_text(mime_type: &str) -> bool {
+    let mime_type = mime_type.to_ascii_lowercase();
+    mime_type.starts_with("text/")
+        || ["json", "javascript", "xml", "x-www-form-urlencoded", "svg"].iter().any(|t| mime_type.contains(t))
+}
+
+/// Headers for a flow record, with credential values masked.
+pub fn headers(headers: &HeaderMap) -> Vec<FlowHeader> {
+    headers
+        .iter()
+        .map(|(name, value)| FlowHeader { name: name.to_string(), value: auth::redacted_value(name, value) })
+        .collect()
+}
+
+pub fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default()
+}
+
+/// Starts the record of a request that has just arrived with an absolute URI.
+pub(crate) fn start(req: &Request<Body>, uri: String, client_info: &ClientInfo) -> FlowEntry {
+    FlowEntry {
+        id: Uuid::new_v4().to_string(),
+        method: req.method().to_string(),
+        uri,
+        protocol: format!("{:?}", req.version()),
+        client_ip: client_info.ip.map(|ip| ip.to_canonical()),
+        user: client_info.user.clone(),
+        started_at_ms: now_ms(),
+        request_headers: headers(req.headers()),
+        ..FlowEntry::default()
+    }
+}
+
+/// Tees the request body into a buffer when body capture is on and there is a body.
+pub(crate) fn capture_request(req: &mut Request<Body>, settings: &Flows) -> Option<Arc<Mutex<BodyBuffer>>> {
+    // Boş gövdeyi sarmalamak is_end_stream bilgisini yok eder; GET yeniden denenemez olur
+    if !settings.capture_bodies || req.body().is_end_stream() {
+        return None;
+    }
+    let buffer = Arc::new(Mutex::new(BodyBuffer::default()));
+    let body = std::mem::replace(req.body_mut(), Body::empty());
+    *req.body_mut() = tee(body, settings.max_body_bytes, buffer.clone(), ());
+    Some(buffer)
+}
+
+/// Notes the origin's protocol and, for a new connection, how long it took to open.
+pub fn record_upstream(flow: &mut FlowEntry, resp: &Response<Body>) {
+    flow.upstream_protocol = Some(format!("{:?}", resp.version()));
+    if let Some(timings) = resp.extensions().get::<ConnectTimings>().and_then(ConnectTimings::claim) {
+        flow.timings.dns_ms = timings.dns.map(ms);
+        flow.timings.connect_ms = Some(ms(timings.connect));
+        flow.timings.tls_ms = timings.tls.map(ms);
+    }
+}
+
+/// Publishes `flow` as the response headers go out, and again once the body has been
+/// handed to the client with the bytes actually sent, the total time and any captured bodies.
+pub(crate) fn track(mut resp: Response<Body>, mut flow: FlowEntry, started: Instant, request_body: Option<Arc<Mutex<BodyBuffer>>>) -> Response<Body> {
+    flow.status_code = resp.status().as_u16();
+    flow.response_headers = headers(resp.headers());
+    flow.timings.ttfb_ms = Some(ms(started.elapsed()));
+    let _ = EVENT_BROADCASTER.send(WsEvent::FlowUpdated { flow: Box::new(flow.clone()) });
+
+    let settings = &crate::config::get().flows;
+    let mut finisher = Finisher {
+        limit: settings.max_body_bytes,
+        response_body: settings.capture_bodies.then(BodyBuffer::default),
+        content_encoding: header_str(resp.headers(), CONTENT_ENCODING),
+        content_type: header_str(resp.headers(), CONTENT_TYPE),
+        body_error: resp.extensions().get::<BodyError>().cloned(),
+        sent: 0,
+        flow,
+        started,
+        request_body,
+    };
+    let body = std::mem::replace(resp.body_mut(), Body::empty());
+    if body.is_end_stream() {
+        // Gövdesiz yanıtları sarmalamak boyut bilgisini (Content-Length: 0) kaybettirir
+        drop(finisher);
+        return resp;
+    }
+    *resp.body_mut() = Body::wrap_stream(body.map(move |chunk| {
+        match &chunk {
+            Ok(bytes) => finisher.sent(bytes),
+            Err(e) => finisher.client_error(e),
+        }
+        chunk
+    }));
+    resp
+}
+
+/// Completes the flow record when the response body stream is dropped.
+struct Finisher {
+    flow: FlowEntry,
+    started: Instant,
+    limit: usize,
+    sent: u64,
+    request_body: Option<Arc<Mutex<BodyBuffer>>>,
+    response_body: Option<BodyBuffer>,
+    content_encoding: String,
+    content_type: String,
+    body_error: Option<BodyError>,
+}
+
+impl Finisher {
+    fn sent(&mut self, chunk: &[u8]) {
+        self.sent += chunk.len() as u64;
+        if let Some(buffer) = &mut self.response_body {
+            buffer.push(chunk, self.limit);
+        }
+    }
+
+    fn client_error(&mut self, err: &hyper::Error) {
+        if self.flow.error.is_none() {
+            self.flow.error = Some(crate::downloader::failure_reason(err));
+        }
+    }
+}
+
+impl Drop for Finisher {
+    fn drop(&mut self) {
+        let mut flow = std::mem::take(&mut self.flow);
+        flow.response_size_bytes = self.sent;
+        flow.timings.total_ms = Some(ms(self.started.elapsed()));
+        // Origin'in asıl hatası, istemcinin gördüğü dolum hatasından daha açıklayıcı
+        if let Some(reason) = self.body_error.as_ref().and_then(|e| e.0.get()) {
+            flow.error = Some(reason.clone());
+        }
+        if let Some(buffer) = &self.request_body {
+            let buffer = buffer.lock().unwrap();
+            if buffer.total > 0 {
+                let content_encoding = request_header(&flow, "content-encoding");
+                let content_type = request_header(&flow, "content-type");
+                flow.request_body = Some(captured(&buffer, &content_encoding, &content_type));
+            }
+        }
+        if let Some(buffer) = self.response_body.as_ref().filter(|b| b.total > 0) {
+            flow.response_body = Some(captured(buffer, &self.content_encoding, &self.content_type));
+        }
+        let _ = EVENT_BROADCASTER.send(WsEvent::FlowUpdated { flow: Box::new(flow) });
+    }
+}
+
+/// Decoded body text, base64 for binary content; no content when a compressed body was truncated.
+fn captured(buffer: &BodyBuffer, content_encoding: &str, content_type: &str) -> CapturedBody {
+    let mut body = CapturedBody { size: buffer.total, truncated: buffer.truncated, text: None, encoding: None };
+    let identity = matches!(content_encoding.trim(), "" | "identity");
+    let Some(decoded) = (if buffer.truncated && !identity { None } else { decode(&buffer.bytes, content_encoding) }) else {
+        return body;
+    };
+    // Content-Type'sız gövdeler geçerli UTF-8 ise metin sayılır
+    if is_text(content_type) || content_type.is_empty() {
+        match String::from_utf8(decoded) {
+            Ok(text) => body.text = Some(text),
+            // Kesme noktası çok baytlı bir karakterin ortasına denk gelebilir
+            Err(e) if buffer.truncated && e.utf8_error().error_len().is_none() => {
+                let valid = e.utf8_error().valid_up_to();
+                let mut bytes = e.into_bytes();
+                bytes.truncate(valid);
+                body.text = String::from_utf8(bytes).ok();
+            }
+            Err(e) => {
+                body.text = Some(BASE64.encode(e.as_bytes()));
+                body.encoding = Some("base64".to_string());
+            }
+        }
+    } else if !decoded.is_empty() {
+        body.text = Some(BASE64.encode(&decoded));
+        body.encoding = Some("base64".to_string());
+    }
+    body
+}
+
+fn request_header(flow: &FlowEntry, name: &str) -> String {
+    flow.request_headers.iter().find(|h| h.name == name).map(|h| h.value.clone()).unwrap_or_default()
+}
+
+fn header_str(headers: &HeaderMap, name: hyper::header::HeaderName) -> String {
+    headers.get(name).and_then(|v| v.to_str().ok()).unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloader::is_retryable;
+
+    fn capturing() -> Flows {
+        Flows { capture_bodies: true, ..Flows::default() }
+    }
+
+    #[test]
+    fn bodiless_get_stays_retryable_with_capture_on() {
+        let mut req = Request::get("http://example.com/").body(Body::empty()).unwrap();
+        assert!(capture_request(&mut req, &capturing()).is_none());
+        assert!(req.body().is_end_stream());
+        assert!(is_retryable(&req));
+    }
+
+    #[tokio::test]
+    async fn captures_request_bodies() {
+        let mut req = Request::put("http://example.com/").body(Body::from("payload")).unwrap();
+        let buffer = capture_request(&mut req, &capturing()).unwrap();
+        assert!(!is_retryable(&req));
+        hyper::body::to_bytes(req.into_body()).await.unwrap();
+        assert_eq!(buffer.lock().unwrap().bytes, b"payload");
+    }
+}
@@ -0,0 +1,526 @@
+// File: crates/service/src/har.rs
+
+use crate::auth;
+use crate::flows::{decode, is_text, tee, BodyBuffer};
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use bytes::Bytes;
+use chrono::{SecondsFormat, Utc};
+use hyper::body::HttpBody;
+use hyper::header::{HeaderMap, HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Body, Method, Request, Response, StatusCode, Version};
+use sentiric_core::{HarStatus, ReplayMode, ReplayStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::Instant;
+use tracing::{info, warn};
+
+static STATE: OnceLock<HarState> = OnceLock::new();
+
+/// In-memory HAR 1.2 recording of proxied exchanges and the loaded replay file.
+struct HarState {
+    recording: AtomicBool,
+    max_entries: usize,
+    max_body_bytes: usize,
+    entries: Mutex<VecDeque<Entry>>,
+    replay: RwLock<Option<Replay>>,
+}
+
+/// The `log` wrapper of a HAR document.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Har {
+    pub log: Log,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Log {
+    pub version: String,
+    pub creator: Creator,
+    #[serde(default)]
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Creator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub started_date_time: String,
+    /// Total time in milliseconds.
+    #[serde(default)]
+    pub time: f64,
+    pub request: HarRequest,
+    pub response: HarResponse,
+    #[serde(default)]
+    pub cache: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    pub timings: Timings,
+    /// Flow the entry was recorded from, as published on `/api/events`.
+    #[serde(rename = "_flowId", default, skip_serializing_if = "Option::is_none")]
+    pub flow_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub headers: Vec<NameValue>,
+    #[serde(default)]
+    pub query_string: Vec<NameValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<PostData>,
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub status_text: String,
+    #[serde(default)]
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub headers: Vec<NameValue>,
+    pub content: Content,
+    #[serde(rename = "redirectURL", default)]
+    pub redirect_url: String,
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameValue {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostData {
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// Bodies are stored decoded, as HAR requires; replayed responses go out without `Content-Encoding`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Content {
+    #[serde(default)]
+    pub size: i64,
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// `base64` when `text` holds binary content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Timings {
+    #[serde(default)]
+    pub send: f64,
+    #[serde(default)]
+    pub wait: f64,
+    #[serde(default)]
+    pub receive: f64,
+}
+
+fn unknown_size() -> i64 {
+    -1
+}
+
+/// Recorded responses for one method and URL, served in order; the last one repeats.
+struct ReplayQueue {
+    responses: Vec<HarResponse>,
+    next: AtomicUsize,
+}
+
+struct Replay {
+    mode: ReplayMode,
+    entries: usize,
+    served: AtomicU64,
+    responses: HashMap<(String, String), ReplayQueue>,
+}
+
+pub fn init(settings: &sentiric_core::Har) -> Result<()> {
+    let state = HarState {
+        recording: AtomicBool::new(settings.record),
+        max_entries: settings.max_entries,
+        max_body_bytes: settings.max_body_bytes,
+        entries: Mutex::new(VecDeque::new()),
+        replay: RwLock::new(None),
+    };
+    if let Some(path) = &settings.replay_file {
+        let content = std::fs::read(path).with_context(|| format!("Failed to read HAR file '{}'", path))?;
+        let har: Har = serde_json::from_slice(&content).with_context(|| format!("Invalid HAR file '{}'", path))?;
+        *state.replay.write().unwrap() = Some(Replay::new(har, settings.replay_mode));
+        info!("Replaying {} ({:?} mode)", path, settings.replay_mode);
+    }
+    if settings.record {
+        info!("HAR recording is on");
+    }
+    STATE.set(state).map_err(|_| anyhow::anyhow!("HAR recorder already initialized"))?;
+    Ok(())
+}
+
+fn state() -> &'static HarState {
+    STATE.get().expect("HAR recorder is not initialized")
+}
+
+pub fn status() -> HarStatus {
+    let state = state();
+    HarStatus {
+        recording: state.recording.load(Ordering::Relaxed),
+        recorded_entries: state.entries.lock().unwrap().len(),
+        replay: state.replay.read().unwrap().as_ref().map(|replay| ReplayStatus {
+            mode: replay.mode,
+            entries: replay.entries,
+            served: replay.served.load(Ordering::Relaxed),
+        }),
+    }
+}
+
+pub fn set_recording(enabled: bool) {
+    state().recording.store(enabled, Ordering::Relaxed);
+    info!("HAR recording {}", if enabled { "started" } else { "stopped" });
+}
+
+pub fn clear() {
+    state().entries.lock().unwrap().clear();
+}
+
+/// The recorded entries as a HAR document.
+pub fn export() -> Har {
+    Har {
+        log: Log {
+            version: "1.2".to_string(),
+            creator: Creator { name: "Sentiric Traffic Cache".to_string(), version: env!("CARGO_PKG_VERSION").to_string() },
+            entries: state().entries.lock().unwrap().iter().cloned().collect(),
+        },
+    }
+}
+
+/// Serves the responses in `har` from now on, replacing any earlier replay.
+pub fn load_replay(har: Har, mode: ReplayMode) {
+    let replay = Replay::new(har, mode);
+    info!("Replaying {} HAR entries ({:?} mode)", replay.entries, mode);
+    *state().replay.write().unwrap() = Some(replay);
+}
+
+pub fn stop_replay() {
+    if state().replay.write().unwrap().take().is_some() {
+        info!("HAR replay stopped");
+    }
+}
+
+impl Replay {
+    fn new(har: Har, mode: ReplayMode) -> Self {
+        let entries = har.log.entries.len();
+        let mut responses: HashMap<(String, String), ReplayQueue> = HashMap::new();
+        for entry in har.log.entries {
+            let key = (entry.request.method.to_ascii_uppercase(), normalize_url(&entry.request.url));
+            responses
+                .entry(key)
+                .or_insert_with(|| ReplayQueue { responses: Vec::new(), next: AtomicUsize::new(0) })
+                .responses
+                .push(entry.response);
+        }
+        Self { mode, entries, served: AtomicU64::new(0), responses }
+    }
+}
+
+/// What replay decided for a request.
+pub enum Replayed {
+    Response(Response<Body>),
+    /// Strict replay and the request is not in the HAR file.
+    Missing,
+    /// Not replaying, or fallback mode without a recorded response.
+    PassThrough,
+}
+
+pub fn replay(method: &Method, url: &str) -> Replayed {
+    let replay = state().replay.read().unwrap();
+    let Some(replay) = replay.as_ref() else {
+        return Replayed::PassThrough;
+    };
+    let key = (method.as_str().to_string(), normalize_url(url));
+    let Some(queue) = replay.responses.get(&key) else {
+        return match replay.mode {
+            ReplayMode::Strict => Replayed::Missing,
+            ReplayMode::Fallback => Replayed::PassThrough,
+        };
+    };
+    let index = queue.next.fetch_add(1, Ordering::Relaxed).min(queue.responses.len() - 1);
+    replay.served.fetch_add(1, Ordering::Relaxed);
+    Replayed::Response(build_response(&queue.responses[index]))
+}
+
+fn build_response(recorded: &HarResponse) -> Response<Body> {
+    let body = match (&recorded.content.text, recorded.content.encoding.as_deref()) {
+        (Some(text), Some("base64")) => BASE64.decode(text).unwrap_or_else(|e| {
+            warn!("Invalid base64 content in HAR entry: {}", e);
+            Vec::new()
+        }),
+        (Some(text), _) => text.clone().into_bytes(),
+        (None, _) => Vec::new(),
+    };
+    let mut resp = Response::new(Body::empty());
+    *resp.status_mut() = StatusCode::from_u16(recorded.status).unwrap_or(StatusCode::OK);
+    for header in &recorded.headers {
+        // HTTP/2 sözde başlıkları ve gövdeyle ilgili çerçeveleme başlıkları yeniden üretilir
+        let name = header.name.to_ascii_lowercase();
+        if name.starts_with(':')
+            || matches!(name.as_str(), "content-encoding" | "content-length" | "transfer-encoding" | "connection" | "keep-alive")
+            || header.value == auth::REDACTED
+        {
+            continue;
+        }
+        if let (Ok(name), Ok(value)) = (hyper::header::HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(&header.value)) {
+            resp.headers_mut().append(name, value);
+        }
+    }
+    if !resp.headers().contains_key(CONTENT_TYPE) && !recorded.content.mime_type.is_empty() {
+        if let Ok(value) = HeaderValue::from_str(&recorded.content.mime_type) {
+            resp.headers_mut().insert(CONTENT_TYPE, value);
+        }
+    }
+    resp.headers_mut().insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+    *resp.body_mut() = Body::from(body);
+    resp
+}
+
+// Varsayılan portlar ve büyük/küçük harf farkları eşleşmeyi bozmasın
+fn normalize_url(url: &str) -> String {
+    url::Url::parse(url).map(|u| u.to_string()).unwrap_or_else(|_| url.to_string())
+}
+
+/// A request being recorded; the entry is stored when its response body ends.
+pub struct PendingEntry {
+    flow_id: String,
+    started_date_time: String,
+    started: Instant,
+    request: HarRequest,
+    request_content_type: String,
+    request_body: Arc<Mutex<BodyBuffer>>,
+}
+
+/// Starts recording `req` if recording is on, teeing its body.
+pub fn record_request(req: &mut Request<Body>, url: &str, flow_id: &str) -> Option<PendingEntry> {
+    let state = state();
+    if !state.recording.load(Ordering::Relaxed) {
+        return None;
+    }
+    let request_body = Arc::new(Mutex::new(BodyBuffer::default()));
+    // Boş gövdeyi sarmalamak is_end_stream bilgisini yok eder; GET yeniden denenemez olur
+    if !req.body().is_end_stream() {
+        let body = std::mem::replace(req.body_mut(), Body::empty());
+        *req.body_mut() = tee(body, state.max_body_bytes, request_body.clone(), ());
+    }
+
+    let url = normalize_url(url);
+    let query_string = url::Url::parse(&url)
+        .map(|u| u.query_pairs().map(|(name, value)| NameValue { name: name.into_owned(), value: value.into_owned() }).collect())
+        .unwrap_or_default();
+    Some(PendingEntry {
+        flow_id: flow_id.to_string(),
+        started_date_time: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        started: Instant::now(),
+        request: HarRequest {
+            method: req.method().to_string(),
+            url,
+            http_version: http_version(req.version()),
+            cookies: Vec::new(),
+            headers: name_values(req.headers()),
+            query_string,
+            post_data: None,
+            headers_size: -1,
+            body_size: -1,
+        },
+        request_content_type: header_str(req.headers(), CONTENT_TYPE),
+        request_body,
+    })
+}
+
+impl PendingEntry {
+    /// Tees the response body; the entry is stored once the client has received it.
+    pub fn record_response(self, mut resp: Response<Body>) -> Response<Body> {
+        let response = HarResponse {
+            status: resp.status().as_u16(),
+            status_text: resp.status().canonical_reason().unwrap_or_default().to_string(),
+            http_version: http_version(resp.version()),
+            cookies: Vec::new(),
+            headers: name_values(resp.headers()),
+            content: Content { mime_type: header_str(resp.headers(), CONTENT_TYPE), ..Content::default() },
+            redirect_url: header_str(resp.headers(), hyper::header::LOCATION),
+            headers_size: -1,
+            body_size: -1,
+        };
+        let is_empty = resp.body().is_end_stream();
+        // Boş gövde çözülecek bir şey taşımaz; kodlama adı hatalı bir yorum üretmesin
+        let content_encoding = if is_empty { String::new() } else { header_str(resp.headers(), CONTENT_ENCODING) };
+        let response_body = Arc::new(Mutex::new(BodyBuffer::default()));
+        let finisher = Finisher {
+            wait_ms: elapsed_ms(self.started),
+            pending: Some(self),
+            response: Some(response),
+            content_encoding,
+            response_body: response_body.clone(),
+        };
+        if is_empty {
+            // Akacak gövde yok; kaydı hemen tamamla ve yanıtı olduğu gibi bırak
+            drop(finisher);
+            return resp;
+        }
+        let body = std::mem::replace(resp.body_mut(), Body::empty());
+        *resp.body_mut() = tee(body, state().max_body_bytes, response_body, finisher);
+        resp
+    }
+}
+
+/// Completes the entry when the response body stream is dropped.
+struct Finisher {
+    pending: Option<PendingEntry>,
+    wait_ms: f64,
+    response: Option<HarResponse>,
+    content_encoding: String,
+    response_body: Arc<Mutex<BodyBuffer>>,
+}
+
+impl Drop for Finisher {
+    fn drop(&mut self) {
+        let (Some(pending), Some(mut response)) = (self.pending.take(), self.response.take()) else {
+            return;
+        };
+        let state = state();
+        let total_ms = elapsed_ms(pending.started);
+
+        let mut request = pending.request;
+        {
+            let body = pending.request_body.lock().unwrap();
+            request.body_size = body.total as i64;
+            if body.total > 0 {
+                request.post_data = Some(if body.truncated {
+                    PostData { mime_type: pending.request_content_type, text: None, comment: Some(too_large(state.max_body_bytes)) }
+                } else {
+                    match String::from_utf8(body.bytes.clone()) {
+                        Ok(text) => PostData { mime_type: pending.request_content_type, text: Some(text), comment: None },
+                        Err(_) => PostData {
+                            mime_type: pending.request_content_type,
+                            text: None,
+                            comment: Some("Binary request body not recorded".to_string()),
+                        },
+                    }
+                });
+            }
+        }
+
+        {
+            let body = self.response_body.lock().unwrap();
+            response.body_size = body.total as i64;
+            if body.truncated {
+                response.content.size = body.total as i64;
+                response.content.comment = Some(too_large(state.max_body_bytes));
+            } else {
+                match decode(&body.bytes, &self.content_encoding) {
+                    Some(decoded) => {
+                        response.content.size = decoded.len() as i64;
+                        if is_text(&response.content.mime_type) {
+                            match String::from_utf8(decoded) {
+                                Ok(text) => response.content.text = Some(text),
+                                Err(e) => {
+                                    response.content.text = Some(BASE64.encode(e.as_bytes()));
+                                    response.content.encoding = Some("base64".to_string());
+                                }
+                            }
+                        } else if !decoded.is_empty() {
+                            response.content.text = Some(BASE64.encode(&decoded));
+                            response.content.encoding = Some("base64".to_string());
+                        }
+                    }
+                    None => {
+                        response.content.size = body.total as i64;
+                        response.content.comment = Some(format!("Body with content-encoding '{}' not recorded", self.content_encoding));
+                    }
+                }
+            }
+        }
+
+        let entry = Entry {
+            started_date_time: pending.started_date_time,
+            time: total_ms,
+            request,
+            response,
+            cache: serde_json::Map::new(),
+            timings: Timings { send: 0.0, wait: self.wait_ms, receive: (total_ms - self.wait_ms).max(0.0) },
+            flow_id: Some(pending.flow_id),
+        };
+        let mut entries = state.entries.lock().unwrap();
+        entries.push_back(entry);
+        while entries.len() > state.max_entries {
+            entries.pop_front();
+        }
+    }
+}
+
+fn too_large(limit: usize) -> String {
+    format!("Body larger than max_body_bytes ({}) not recorded", limit)
+}
+
+fn name_values(headers: &HeaderMap) -> Vec<NameValue> {
+    headers
+        .iter()
+        .map(|(name, value)| NameValue { name: name.to_string(), value: auth::redacted_value(name, value) })
+        .collect()
+}
+
+fn header_str(headers: &HeaderMap, name: hyper::header::HeaderName) -> String {
+    headers.get(name).and_then(|v| v.to_str().ok()).unwrap_or_default().to_string()
+}
+
+fn http_version(version: Version) -> String {
+    match version {
+        Version::HTTP_09 => "HTTP/0.9",
+        Version::HTTP_10 => "HTTP/1.0",
+        Version::HTTP_2 => "HTTP/2.0",
+        Version::HTTP_3 => "HTTP/3.0",
+        _ => "HTTP/1.1",
+    }
+    .to_string()
+}
+
+fn elapsed_ms(since: Instant) -> f64 {
+    since.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Parses an uploaded HAR document.
+pub fn parse(body: &Bytes) -> Result<Har> {
+    serde_json::from_slice(body).context("Invalid HAR document")
+}
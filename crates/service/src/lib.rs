@@ -5,40 +5,66 @@
 use crate::cache::CacheManager;
 use crate::management::EVENT_BROADCASTER;
 use anyhow::Result;
-use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
 // Modülleri tanımlıyoruz
+pub mod acl;
+pub mod auth;
 pub mod cache;
 pub mod certs;
 pub mod config;
 pub mod downloader;
+pub mod flows;
+pub mod har;
+pub mod listen;
 pub mod dns;
 pub mod management;
+pub mod mapping;
+pub mod mock;
+pub mod pages;
+pub mod passthrough;
 pub mod proxy;
+pub mod proxy_protocol;
+pub mod resolver;
+pub mod rewrite;
 pub mod rules; // <-- YENİ
+pub mod shutdown;
+pub mod throttle;
+pub mod socks;
+pub mod transparent;
+pub mod upstream;
+pub mod upstream_tls;
+pub mod websocket;
 
 pub async fn run() -> Result<()> {
     let subscriber = FmtSubscriber::builder().with_env_filter(EnvFilter::from_default_env().add_directive("info".parse()?)).with_thread_ids(true).finish();
     tracing::subscriber::set_global_default(subscriber)?;
     config::init()?;
     let settings = config::get();
+    upstream::validate(&settings.upstream)?;
+    auth::init(&settings.proxy.auth)?;
+    transparent::validate(settings)?;
+    resolver::init(settings)?;
+    upstream_tls::init(&settings.upstream_tls)?;
+    pages::init(&settings.error_pages)?;
+    har::init(&settings.har)?;
     info!("Configuration loaded successfully.");
     let ca = Arc::new(certs::CertificateAuthority::new(&settings.certs.path)?);
     info!("Certificate Authority is ready.");
     let cache_manager = Arc::new(CacheManager::new(&settings.cache.path)?);
     info!("Cache Manager is ready.");
+    let passthrough = Arc::new(passthrough::PassthroughRegistry::new(
+        settings.proxy.tls_passthrough.clone(),
+        &settings.certs.path,
+    )?);
 
     // --- GÖREVLERİ OLUŞTUR ---
 
-    let proxy_addr: SocketAddr = format!("{}:{}", settings.proxy.bind_address, settings.proxy.port).parse()?;
-    let proxy_task = tokio::spawn(proxy::run_server(proxy_addr, ca, cache_manager.clone()));
-
-    let mgmt_addr: SocketAddr = format!("{}:{}", settings.management.bind_address, settings.management.port).parse()?;
-    let mgmt_task = tokio::spawn(management::run_server(mgmt_addr, cache_manager.clone()));
+    let proxy_task = tokio::spawn(proxy::run_server(ca.clone(), cache_manager.clone(), passthrough.clone()));
+    let mgmt_task = tokio::spawn(management::run_server(cache_manager.clone()));
 
     let dns_task = if settings.dns.enabled {
         let dns_settings = settings.clone();
@@ -50,9 +76,20 @@ pub async fn run() -> Result<()> {
         None
     };
 
+    let transparent_task = if settings.transparent.enabled {
+        let transparent_settings = settings.transparent.clone();
+        let cache = cache_manager.clone();
+        Some(tokio::spawn(async move {
+            transparent::run_servers(&transparent_settings, ca, cache, passthrough).await
+        }))
+    } else {
+        None
+    };
+
+    let stats_cache = cache_manager.clone();
     let stats_broadcaster_task = tokio::spawn(async move {
         loop {
-            let stats = cache_manager.get_stats().await;
+            let stats = stats_cache.get_stats().await;
             // ======================== DÜZELTME BAŞLANGICI ========================
             // WsEvent'i yeni struct variant yapısına uygun olarak oluştur.
             let _ = EVENT_BROADCASTER.send(management::WsEvent::StatsUpdated { stats });
@@ -63,7 +100,7 @@ pub async fn run() -> Result<()> {
 
     info!("All services running. Press Ctrl+C to exit.");
     tokio::select! {
-        _ = tokio::signal::ctrl_c() => { info!("Shutdown signal received."); }
+        _ = shutdown_signal() => { info!("Shutdown signal received."); }
         res = proxy_task => { if let Err(e) = res? { error!("Proxy server exited: {}", e); } }
         _res = mgmt_task => { error!("Management server exited."); }
         
@@ -78,7 +115,47 @@ pub async fn run() -> Result<()> {
                 Err(e) => error!("DNS server task failed to execute: {}", e),
              }
         }
+        res = async { if let Some(task) = transparent_task { task.await } else { futures_util::future::pending().await } } => {
+             match res {
+                Ok(Ok(_)) => info!("Transparent listeners exited cleanly."),
+                Ok(Err(e)) => error!("Transparent listeners exited with an application error: {}", e),
+                Err(e) => error!("Transparent listener task failed to execute: {}", e),
+             }
+        }
         _ = stats_broadcaster_task => { info!("Stats broadcaster exited."); }
     }
+
+    // Yeni bağlantı kabulünü durdur, açık isteklerin ve önbellek dolumlarının bitmesini bekle.
+    shutdown::trigger();
+    let grace = Duration::from_secs(settings.shutdown.grace_period_secs);
+    info!("Draining {} active connection(s) and cache fill(s) (up to {}s)...", shutdown::active(), grace.as_secs());
+    if shutdown::drain(grace).await {
+        info!("Shutdown complete.");
+    } else {
+        warn!("Grace period elapsed with {} task(s) still running; discarding unfinished cache fills.", shutdown::active());
+        cache_manager.discard_in_flight();
+    }
     Ok(())
+}
+
+/// Resolves on Ctrl+C, or on SIGTERM (e.g. `docker stop`) on Unix.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = sigterm.recv() => {}
+                }
+            }
+            Err(e) => {
+                warn!("Failed to install SIGTERM handler: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
 }
\ No newline at end of file
@@ -0,0 +1,65 @@
+// File: crates/service/src/listen.rs
+
+use anyhow::{bail, Context, Result};
+use network_interface::{Addr, NetworkInterface, NetworkInterfaceConfig};
+use sentiric_core::BindAddresses;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::net::{IpAddr, SocketAddr, SocketAddrV6};
+use tokio::net::{TcpListener, UdpSocket};
+
+/// Expands `addresses` into socket addresses on `port`; interface names yield every address of the interface.
+pub fn socket_addrs(addresses: &BindAddresses, port: u16) -> Result<Vec<SocketAddr>> {
+    let mut addrs = Vec::new();
+    for address in addresses.iter() {
+        // `[::]` biçimi de kabul edilsin
+        if let Ok(ip) = address.trim_matches(|c| c == '[' || c == ']').parse::<IpAddr>() {
+            addrs.push(SocketAddr::new(ip, port));
+            continue;
+        }
+        let interfaces = NetworkInterface::show().context("Failed to list network interfaces")?;
+        let before = addrs.len();
+        for interface in interfaces.iter().filter(|i| i.name == address) {
+            match interface.addr {
+                Some(Addr::V4(v4)) => addrs.push(SocketAddr::new(IpAddr::V4(v4.ip), port)),
+                // Link-local adresler arayüz indeksi olmadan bağlanamaz
+                Some(Addr::V6(v6)) => {
+                    let scope_id = if v6.ip.segments()[0] & 0xffc0 == 0xfe80 { interface.index } else { 0 };
+                    addrs.push(SocketAddr::V6(SocketAddrV6::new(v6.ip, port, 0, scope_id)));
+                }
+                None => {}
+            }
+        }
+        if addrs.len() == before {
+            bail!("'{}' is neither an IP address nor a network interface with an address", address);
+        }
+    }
+    if addrs.is_empty() {
+        bail!("No bind address configured");
+    }
+    Ok(addrs)
+}
+
+/// Binds a TCP listener. IPv6 sockets are IPv6-only so `0.0.0.0` and `::` can be listed together.
+pub fn bind_tcp(addr: SocketAddr) -> Result<TcpListener> {
+    let socket = socket(addr, Type::STREAM, Protocol::TCP)?;
+    socket.listen(1024).with_context(|| format!("Failed to listen on {}", addr))?;
+    TcpListener::from_std(socket.into()).with_context(|| format!("Failed to register listener on {}", addr))
+}
+
+pub fn bind_udp(addr: SocketAddr) -> Result<UdpSocket> {
+    let socket = socket(addr, Type::DGRAM, Protocol::UDP)?;
+    UdpSocket::from_std(socket.into()).with_context(|| format!("Failed to register socket on {}", addr))
+}
+
+fn socket(addr: SocketAddr, kind: Type, protocol: Protocol) -> Result<Socket> {
+    let socket = Socket::new(Domain::for_address(addr), kind, Some(protocol))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    if kind == Type::STREAM {
+        socket.set_reuse_address(true)?;
+    }
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into()).with_context(|| format!("Failed to bind {}", addr))?;
+    Ok(socket)
+}
@@ -1,17 +1,27 @@
 // File: crates/service/src/management.rs
 
+use crate::acl::{AccessControl, Listener};
 use crate::cache::CacheManager;
 use crate::config;
+use crate::har;
+use crate::listen;
+use crate::shutdown;
+use crate::throttle;
 use anyhow::Result;
 use futures_util::{StreamExt, SinkExt};
-use sentiric_core::{Stats, FlowEntry};
+use hyper::server::conn::Http;
+use hyper::service::{service_fn, Service};
+use hyper::{Body, Request, Response};
+use std::convert::Infallible;
+use sentiric_core::{Stats, FlowEntry, ReplayMode, WebSocketFrame};
 use std::net::SocketAddr;
 use std::sync::Arc;
+use tokio::net::TcpListener;
 use tokio::sync::broadcast::{self, Sender};
 use warp::ws::{Message, WebSocket};
 use warp::Filter;
-use serde::Serialize;
-use tracing::{info, warn};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
 
 // ======================== DÜZELTME BAŞLANGICI ========================
 // Enum'ı tuple variant'tan struct variant'a dönüştürüyoruz.
@@ -22,7 +32,8 @@ use tracing::{info, warn};
 #[serde(tag = "type")]
 pub enum WsEvent {
     StatsUpdated { stats: Stats },
-    FlowUpdated { flow: FlowEntry },
+    FlowUpdated { flow: Box<FlowEntry> },
+    WebSocketFrame { frame: WebSocketFrame },
 }
 // ========================= DÜZELTME BİTİŞİ =========================
 
@@ -30,8 +41,16 @@ lazy_static::lazy_static! {
     pub static ref EVENT_BROADCASTER: Sender<WsEvent> = broadcast::channel(128).0;
 }
 
-pub async fn run_server(addr: SocketAddr, cache: Arc<CacheManager>) -> Result<()> {
-    info!("🚀 Management server listening on http://{}", addr);
+/// Client address of a management connection, attached to each request for the access list.
+#[derive(Clone, Copy)]
+struct RemoteAddr(SocketAddr);
+
+pub async fn run_server(cache: Arc<CacheManager>) -> Result<()> {
+    let settings = &config::get().management;
+    let listeners = listen::socket_addrs(&settings.bind_address, settings.port)?
+        .into_iter()
+        .map(listen::bind_tcp)
+        .collect::<Result<Vec<_>>>()?;
 
     let cache_filter = warp::any().map(move || cache.clone());
 
@@ -54,11 +73,59 @@ pub async fn run_server(addr: SocketAddr, cache: Arc<CacheManager>) -> Result<()
         .and(warp::get())
         .and_then(handle_list_rules);
 
+    let throttle_route = warp::path!("api" / "throttle")
+        .and(warp::get())
+        .and_then(handle_throttle_status);
+
+    let har_export_route = warp::path!("api" / "har")
+        .and(warp::get())
+        .map(handle_har_export);
+
+    let har_clear_route = warp::path!("api" / "har")
+        .and(warp::delete())
+        .map(|| {
+            har::clear();
+            warp::reply::json(&har::status())
+        });
+
+    let har_status_route = warp::path!("api" / "har" / "status")
+        .and(warp::get())
+        .map(|| warp::reply::json(&har::status()));
+
+    let har_recording_route = warp::path!("api" / "har" / "recording")
+        .and(warp::post())
+        .and(warp::body::json())
+        .map(|body: RecordingRequest| {
+            har::set_recording(body.enabled);
+            warp::reply::json(&har::status())
+        });
+
+    let har_replay_route = warp::path!("api" / "har" / "replay")
+        .and(warp::post())
+        .and(warp::query::<ReplayQuery>())
+        .and(warp::body::content_length_limit(HAR_UPLOAD_LIMIT))
+        .and(warp::body::bytes())
+        .map(handle_har_replay);
+
+    let har_stop_replay_route = warp::path!("api" / "har" / "replay")
+        .and(warp::delete())
+        .map(|| {
+            har::stop_replay();
+            warp::reply::json(&har::status())
+        });
+
+    let har_routes = har_export_route
+        .or(har_clear_route)
+        .or(har_status_route)
+        .or(har_recording_route)
+        .or(har_replay_route)
+        .or(har_stop_replay_route);
+
     let events_route = warp::path!("api" / "events")
         .and(warp::ws())
         .map(|ws: warp::ws::Ws| ws.on_upgrade(handle_websocket_connection));
     
-    let api_routes = stats_route.or(entries_route).or(clear_route).or(rules_route).or(events_route);
+    let api_routes = stats_route.or(entries_route).or(clear_route).or(rules_route).or(throttle_route).or(har_routes).or(events_route);
 
     let pac_route = warp::path!("proxy.pac")
         .and(warp::get())
@@ -67,13 +134,106 @@ pub async fn run_server(addr: SocketAddr, cache: Arc<CacheManager>) -> Result<()
     let static_files = warp::fs::dir("web/dist")
         .or(warp::fs::file("web/dist/index.html"));
 
-    let routes = api_routes.or(pac_route).or(static_files);
+    let access = Arc::new(AccessControl::new(Listener::Management, &settings.access)?);
+    let access_filter = warp::ext::optional::<RemoteAddr>()
+        .and_then(move |remote: Option<RemoteAddr>| {
+            let access = access.clone();
+            async move {
+                match remote {
+                    Some(RemoteAddr(addr)) if !access.check(addr.ip()) => Err(warp::reject::custom(AccessDenied)),
+                    _ => Ok(()),
+                }
+            }
+        })
+        .untuple_one();
+
+    let routes = access_filter
+        .and(api_routes.or(pac_route).or(static_files))
+        .recover(handle_rejection);
 
-    warp::serve(routes).run(addr).await;
+    // warp kendi bağladığı soketlerde IPv6-only ayarlayamıyor; dinleyicileri biz açıp bağlantıları
+    // warp servisine veriyoruz. İstemci adresi, erişim listesi için isteğe eklenir.
+    let service = warp::service(routes);
+    let servers = listeners.into_iter().map(|listener| serve(listener, service.clone()));
+    futures_util::future::try_join_all(servers).await?;
+    info!("Management server stopped.");
 
     Ok(())
 }
 
+/// Serves `service` on `listener` until shutdown, tagging each request with the client address.
+async fn serve<S>(listener: TcpListener, service: S) -> Result<()>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    info!("🚀 Management server listening on http://{}", listener.local_addr()?);
+    loop {
+        let (stream, remote) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = shutdown::requested() => return Ok(()),
+        };
+        let service = service.clone();
+        let connection_service = service_fn(move |mut req: Request<Body>| {
+            req.extensions_mut().insert(RemoteAddr(remote));
+            let response = service.clone().call(req);
+            async move { Ok::<_, hyper::Error>(response.await.unwrap_or_else(|never| match never {})) }
+        });
+        tokio::spawn(async move {
+            if let Err(e) = shutdown::serve_connection(&Http::new(), stream, connection_service).await {
+                debug!(cause = ?e, "Management connection error");
+            }
+        });
+    }
+}
+
+#[derive(Debug)]
+struct AccessDenied;
+
+impl warp::reject::Reject for AccessDenied {}
+
+async fn handle_rejection(err: warp::Rejection) -> Result<impl warp::Reply, warp::Rejection> {
+    if err.find::<AccessDenied>().is_some() {
+        return Ok(warp::reply::with_status("Forbidden", warp::http::StatusCode::FORBIDDEN));
+    }
+    Err(err)
+}
+
+const HAR_UPLOAD_LIMIT: u64 = 512 * 1024 * 1024;
+
+#[derive(Deserialize)]
+struct RecordingRequest {
+    enabled: bool,
+}
+
+#[derive(Deserialize)]
+struct ReplayQuery {
+    mode: Option<ReplayMode>,
+}
+
+fn handle_har_export() -> impl warp::Reply {
+    warp::reply::with_header(
+        warp::reply::json(&har::export()),
+        "Content-Disposition",
+        "attachment; filename=\"sentiric.har\"",
+    )
+}
+
+fn handle_har_replay(query: ReplayQuery, body: bytes::Bytes) -> warp::reply::Response {
+    use warp::Reply;
+    match har::parse(&body) {
+        Ok(document) => {
+            har::load_replay(document, query.mode.unwrap_or(config::get().har.replay_mode));
+            warp::reply::json(&har::status()).into_response()
+        }
+        Err(e) => warp::reply::with_status(format!("{:#}", e), http::StatusCode::BAD_REQUEST).into_response(),
+    }
+}
+
+async fn handle_throttle_status() -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&throttle::status()))
+}
+
 async fn handle_list_rules() -> Result<impl warp::Reply, warp::Rejection> {
     let rules = &config::get().rules;
     Ok(warp::reply::json(rules))
@@ -0,0 +1,111 @@
+// File: crates/service/src/mapping.rs
+
+use anyhow::{Context, Result};
+use hyper::header::{HeaderValue, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Body, Method, Response, StatusCode, Uri};
+use std::path::{Component, Path, PathBuf};
+use tokio_util::io::ReaderStream;
+use tracing::debug;
+
+/// The URI a `map-remote` rule sends `uri` to. Targets ending in `/` keep the request
+/// path below them; other targets replace the path. The query is kept unless the target has one.
+pub fn remote_uri(target: &str, uri: &Uri) -> Result<Uri> {
+    let mut mapped = url::Url::parse(target).with_context(|| format!("Invalid map-remote target '{}'", target))?;
+    if mapped.path().ends_with('/') {
+        let path = format!("{}{}", mapped.path(), uri.path().trim_start_matches('/'));
+        mapped.set_path(&path);
+    }
+    if mapped.query().is_none() {
+        mapped.set_query(uri.query());
+    }
+    mapped.as_str().parse().with_context(|| format!("Invalid map-remote target '{}'", mapped))
+}
+
+/// Answers a `map-local` rule from `target`: the file itself, or the request path
+/// under it if it is a directory (`index.html` for directories below that).
+/// Errors carry the status and reason for the error page.
+pub async fn serve_local(target: &str, uri: &Uri, method: &Method) -> Result<Response<Body>, (StatusCode, &'static str)> {
+    let Some(path) = local_path(Path::new(target), uri.path()).await else {
+        return Err((StatusCode::FORBIDDEN, "Path escapes the map-local directory"));
+    };
+    let file = match tokio::fs::File::open(&path).await {
+        Ok(file) => file,
+        Err(e) => {
+            debug!("map-local could not open {:?}: {}", path, e);
+            return Err((StatusCode::NOT_FOUND, "Not found in map-local directory"));
+        }
+    };
+    let len = match file.metadata().await {
+        Ok(metadata) if metadata.is_file() => metadata.len(),
+        _ => return Err((StatusCode::NOT_FOUND, "Not found in map-local directory")),
+    };
+
+    let body = if method == Method::HEAD { Body::empty() } else { Body::wrap_stream(ReaderStream::new(file)) };
+    let mut resp = Response::new(body);
+    let content_type = mime_guess::from_path(&path).first_or_octet_stream();
+    if let Ok(value) = HeaderValue::from_str(content_type.as_ref()) {
+        resp.headers_mut().insert(CONTENT_TYPE, value);
+    }
+    resp.headers_mut().insert(CONTENT_LENGTH, HeaderValue::from(len));
+    // Yerel dosyalar geliştirme sırasında değişir; tarayıcı her seferinde yeniden istesin
+    resp.headers_mut().insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    Ok(resp)
+}
+
+/// Resolves the file to serve; `None` if the request path tries to leave `target`.
+async fn local_path(target: &Path, request_path: &str) -> Option<PathBuf> {
+    if !tokio::fs::metadata(target).await.is_ok_and(|m| m.is_dir()) {
+        return Some(target.to_path_buf());
+    }
+    let decoded = percent_encoding::percent_decode_str(request_path).decode_utf8_lossy();
+    let mut path = target.to_path_buf();
+    for component in Path::new(decoded.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    if tokio::fs::metadata(&path).await.is_ok_and(|m| m.is_dir()) {
+        path.push("index.html");
+    }
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sentiric-mapping-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("docs/api")).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn serves_nested_paths_inside_the_target() {
+        let root = temp_root();
+        assert_eq!(local_path(&root, "/docs/api/index.json").await, Some(root.join("docs/api/index.json")));
+        assert_eq!(local_path(&root, "/docs/api").await, Some(root.join("docs/api/index.html")));
+        assert_eq!(local_path(&root, "/docs/./readme%20first.txt").await, Some(root.join("docs/readme first.txt")));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_parent_traversal() {
+        let root = temp_root();
+        assert_eq!(local_path(&root, "/../secret").await, None);
+        assert_eq!(local_path(&root, "/docs/../../secret").await, None);
+        assert_eq!(local_path(&root, "/%2e%2e/secret").await, None);
+        assert_eq!(local_path(&root, "/docs/%2E%2E%2F%2E%2E%2Fsecret").await, None);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn absolute_paths_stay_under_the_target() {
+        let root = temp_root();
+        assert_eq!(local_path(&root, "//etc/passwd").await, Some(root.join("etc/passwd")));
+        assert_eq!(local_path(&root, "/%2Fetc%2Fpasswd").await, Some(root.join("etc/passwd")));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
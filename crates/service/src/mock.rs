@@ -0,0 +1,79 @@
+// File: crates/service/src/mock.rs
+
+use anyhow::{bail, Result};
+use hyper::header::{HeaderName, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Body, Response, StatusCode};
+use sentiric_core::MockResponse;
+use std::time::Duration;
+use tracing::warn;
+
+/// Rejects mock settings that could only be honoured by guessing.
+pub fn validate(mock: &MockResponse) -> Result<()> {
+    for (field, status) in [("status", mock.status), ("failure_status", mock.failure_status)] {
+        if !(100..=599).contains(&status) {
+            bail!("mock {} {} is not an HTTP status code", field, status);
+        }
+    }
+    if !(0.0..=1.0).contains(&mock.failure_rate) {
+        bail!("mock failure_rate {} must be between 0.0 and 1.0", mock.failure_rate);
+    }
+    for (name, value) in &mock.headers {
+        if HeaderName::from_bytes(name.as_bytes()).is_err() || HeaderValue::from_str(value).is_err() {
+            bail!("mock header {:?}: {:?} is not a valid HTTP header", name, value);
+        }
+    }
+    Ok(())
+}
+
+/// Builds the response for a `mock` rule, after its optional latency; the flag tells
+/// whether a random failure was injected instead.
+pub async fn respond(mock: &MockResponse) -> (Response<Body>, bool) {
+    if mock.delay_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(mock.delay_ms)).await;
+    }
+
+    if mock.failure_rate > 0.0 && rand::random::<f64>() < mock.failure_rate {
+        let status = StatusCode::from_u16(mock.failure_status).unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+        return (text_response(status, "Failure injected by mock rule"), true);
+    }
+
+    let body = match &mock.body_file {
+        Some(path) => match tokio::fs::read(path).await {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("Failed to read mock body file '{}': {}", path, e);
+                return (text_response(StatusCode::INTERNAL_SERVER_ERROR, "Mock body file is unreadable"), false);
+            }
+        },
+        None => mock.body.clone().unwrap_or_default().into_bytes(),
+    };
+
+    let mut resp = Response::new(Body::empty());
+    *resp.status_mut() = StatusCode::from_u16(mock.status).unwrap_or(StatusCode::OK);
+    if let Some(path) = &mock.body_file {
+        // Başlıklarda açıkça verilmediyse dosya uzantısından tahmin et
+        if let Some(content_type) = mime_guess::from_path(path).first() {
+            if let Ok(value) = HeaderValue::from_str(content_type.as_ref()) {
+                resp.headers_mut().insert(CONTENT_TYPE, value);
+            }
+        }
+    }
+    for (name, value) in &mock.headers {
+        match (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+            (Ok(name), Ok(value)) => {
+                resp.headers_mut().insert(name, value);
+            }
+            _ => warn!("Skipping invalid mock header {:?}: {:?}", name, value),
+        }
+    }
+    resp.headers_mut().insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+    *resp.body_mut() = Body::from(body);
+    (resp, false)
+}
+
+fn text_response(status: StatusCode, message: &'static str) -> Response<Body> {
+    let mut resp = Response::new(Body::from(message));
+    *resp.status_mut() = status;
+    resp.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("text/plain; charset=utf-8"));
+    resp
+}
@@ -0,0 +1,217 @@
+// File: crates/service/src/pages.rs
+
+use crate::downloader::FailureKind;
+use anyhow::{Context, Result};
+use chrono::{SecondsFormat, Utc};
+use hyper::header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE};
+use hyper::{Body, Response, StatusCode};
+use sentiric_core::ErrorPages;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use tracing::info;
+
+static TEMPLATES: OnceLock<HashMap<PageKind, String>> = OnceLock::new();
+
+const DEFAULT_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{{title}} · Sentiric Traffic Cache</title>
+<style>
+body { font-family: system-ui, sans-serif; background: #0f172a; color: #e2e8f0; margin: 0; padding: 4rem 1rem; }
+main { max-width: 42rem; margin: 0 auto; }
+h1 { margin: 0 0 .5rem; font-size: 1.6rem; }
+.status { color: #38bdf8; font-weight: 600; letter-spacing: .05em; }
+dl { display: grid; grid-template-columns: max-content 1fr; gap: .4rem 1rem; margin-top: 2rem; font-size: .9rem; }
+dt { color: #94a3b8; }
+dd { margin: 0; word-break: break-all; font-family: ui-monospace, monospace; }
+</style>
+</head>
+<body>
+<main>
+<p class="status">{{status}}</p>
+<h1>{{title}}</h1>
+<p>{{message}}</p>
+<dl>
+<dt>URL</dt><dd>{{url}}</dd>
+<dt>Rule</dt><dd>{{rule}}</dd>
+<dt>Detail</dt><dd>{{detail}}</dd>
+<dt>Flow ID</dt><dd>{{flow_id}}</dd>
+<dt>Time</dt><dd>{{timestamp}}</dd>
+</dl>
+</main>
+</body>
+</html>
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PageKind {
+    Block,
+    Timeout,
+    Tls,
+    Dns,
+    /// Not in the cache and the origin cannot be reached.
+    Offline,
+    Upstream,
+    /// Strict HAR replay and the request is not in the file.
+    Unrecorded,
+    /// A `map-local` rule matched but the file cannot be served.
+    LocalFile,
+    /// The request names no usable target, e.g. no `Host` header on a transparent listener.
+    BadRequest,
+}
+
+impl PageKind {
+    const ALL: [PageKind; 9] = [
+        PageKind::Block,
+        PageKind::Timeout,
+        PageKind::Tls,
+        PageKind::Dns,
+        PageKind::Offline,
+        PageKind::Upstream,
+        PageKind::Unrecorded,
+        PageKind::LocalFile,
+        PageKind::BadRequest,
+    ];
+
+    pub fn for_failure(kind: FailureKind) -> Self {
+        match kind {
+            FailureKind::Timeout => PageKind::Timeout,
+            FailureKind::Certificate => PageKind::Tls,
+            FailureKind::Dns => PageKind::Dns,
+            FailureKind::Unreachable => PageKind::Offline,
+            FailureKind::Loop | FailureKind::Other => PageKind::Upstream,
+        }
+    }
+
+    /// Template file name and the `error` field of JSON responses.
+    fn name(self) -> &'static str {
+        match self {
+            PageKind::Block => "block",
+            PageKind::Timeout => "timeout",
+            PageKind::Tls => "tls",
+            PageKind::Dns => "dns",
+            PageKind::Offline => "offline",
+            PageKind::Upstream => "upstream",
+            PageKind::Unrecorded => "unrecorded",
+            PageKind::LocalFile => "local-file",
+            PageKind::BadRequest => "bad-request",
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            PageKind::Block => "Request blocked",
+            PageKind::Timeout => "Origin timed out",
+            PageKind::Tls => "Untrusted origin certificate",
+            PageKind::Dns => "Origin not found",
+            PageKind::Offline => "Origin unreachable",
+            PageKind::Upstream => "Upstream request failed",
+            PageKind::Unrecorded => "Request not recorded",
+            PageKind::LocalFile => "Local file unavailable",
+            PageKind::BadRequest => "Bad request",
+        }
+    }
+
+    fn message(self) -> &'static str {
+        match self {
+            PageKind::Block => "This request was blocked by a Sentiric Traffic Cache rule.",
+            PageKind::Timeout => "The origin server did not respond in time.",
+            PageKind::Tls => "The TLS certificate presented by the origin could not be verified. If it uses an internal CA, add it to upstream_tls.ca_bundle.",
+            PageKind::Dns => "The origin's host name could not be resolved.",
+            PageKind::Offline => "This resource is not in the cache and the origin server cannot be reached.",
+            PageKind::Upstream => "The origin server could not complete the request.",
+            PageKind::Unrecorded => "The proxy is replaying a HAR file in strict mode and this request is not in it.",
+            PageKind::LocalFile => "A map-local rule matched this request, but the local file could not be served.",
+            PageKind::BadRequest => "The proxy could not tell which site this request is for.",
+        }
+    }
+}
+
+/// Loads user templates; kinds without a template use the built-in page.
+///
+/// Templates use `{{name}}` placeholders: `title`, `message`, `status`, `rule`,
+/// `url`, `flow_id`, `timestamp` and `detail`. Values are HTML-escaped.
+pub fn init(settings: &ErrorPages) -> Result<()> {
+    let mut templates = HashMap::new();
+    if let Some(dir) = &settings.templates_dir {
+        for kind in PageKind::ALL {
+            let path = Path::new(dir).join(format!("{}.html", kind.name()));
+            if path.exists() {
+                let template = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read error page template {:?}", path))?;
+                templates.insert(kind, template);
+            }
+        }
+        info!("Loaded {} error page template(s) from {}", templates.len(), dir);
+    }
+    TEMPLATES.set(templates).map_err(|_| anyhow::anyhow!("Error pages already initialized"))?;
+    Ok(())
+}
+
+/// Replaces every `{{name}}` in `template` with the matching value.
+pub fn fill_placeholders<V: AsRef<str>>(template: &str, values: &[(&str, V)]) -> String {
+    values.iter().fold(template.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{{{}}}}}", name), value.as_ref())
+    })
+}
+
+/// Whether the client asked for JSON rather than HTML.
+pub fn wants_json(headers: &HeaderMap) -> bool {
+    let accept = headers.get(ACCEPT).and_then(|v| v.to_str().ok()).unwrap_or_default();
+    accept.contains("json") && !accept.contains("text/html")
+}
+
+/// What the page reports about the request.
+pub struct PageContext<'a> {
+    pub status: StatusCode,
+    pub rule: Option<&'a str>,
+    pub url: &'a str,
+    pub flow_id: &'a str,
+    pub detail: Option<&'a str>,
+    pub json: bool,
+}
+
+/// Renders the page for `kind` from its template, or as JSON when `ctx.json` is set.
+pub fn render(kind: PageKind, ctx: PageContext) -> Response<Body> {
+    let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let (body, content_type) = if ctx.json {
+        let body = serde_json::json!({
+            "error": kind.name(),
+            "status": ctx.status.as_u16(),
+            "title": kind.title(),
+            "message": kind.message(),
+            "rule": ctx.rule,
+            "url": ctx.url,
+            "flowId": ctx.flow_id,
+            "timestamp": timestamp,
+            "detail": ctx.detail,
+        });
+        (body.to_string(), "application/json")
+    } else {
+        let template = TEMPLATES.get().and_then(|t| t.get(&kind)).map(String::as_str).unwrap_or(DEFAULT_TEMPLATE);
+        let status = ctx.status.to_string();
+        let values = [
+            ("title", kind.title()),
+            ("message", kind.message()),
+            ("status", status.as_str()),
+            ("rule", ctx.rule.unwrap_or("—")),
+            ("url", ctx.url),
+            ("flow_id", ctx.flow_id),
+            ("timestamp", timestamp.as_str()),
+            ("detail", ctx.detail.unwrap_or("—")),
+        ];
+        let escaped: Vec<(&str, String)> = values.iter().map(|(name, value)| (*name, html_escape(value))).collect();
+        (fill_placeholders(template, &escaped), "text/html; charset=utf-8")
+    };
+
+    let mut resp = Response::new(Body::from(body));
+    *resp.status_mut() = ctx.status;
+    resp.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    resp
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,95 @@
+// File: crates/service/src/passthrough.rs
+
+use anyhow::{Context, Result};
+use rustls::AlertDescription;
+use sentiric_core::TlsPassthrough;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+/// Hosts that are tunnelled without interception because clients keep
+/// rejecting our leaf certificates (certificate pinning, strict SSO clients...).
+///
+/// Learned hosts are persisted one per line so they survive restarts; delete a
+/// line from the file to intercept that host again.
+pub struct PassthroughRegistry {
+    settings: TlsPassthrough,
+    store_path: PathBuf,
+    learned: Mutex<HashSet<String>>,
+    failures: Mutex<HashMap<String, u32>>,
+}
+
+impl PassthroughRegistry {
+    pub fn new(settings: TlsPassthrough, certs_path: &str) -> Result<Self> {
+        let store_path = Path::new(certs_path).join("learned-passthrough.txt");
+        let learned = match std::fs::read_to_string(&store_path) {
+            Ok(content) => content
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(str::to_string)
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(e).context("Failed to read learned TLS passthrough hosts"),
+        };
+        if !learned.is_empty() {
+            info!("Loaded {} learned TLS passthrough hosts", learned.len());
+        }
+        Ok(Self {
+            settings,
+            store_path,
+            learned: Mutex::new(learned),
+            failures: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn is_learned(&self, host: &str) -> bool {
+        self.learned.lock().unwrap().contains(host)
+    }
+
+    pub fn record_handshake_success(&self, host: &str) {
+        self.failures.lock().unwrap().remove(host);
+    }
+
+    /// Counts a failed client handshake and learns the host once the threshold is reached.
+    pub fn record_handshake_failure(&self, host: &str) {
+        if !self.settings.auto_learn {
+            return;
+        }
+        let count = {
+            let mut failures = self.failures.lock().unwrap();
+            let count = failures.entry(host.to_string()).or_insert(0);
+            *count += 1;
+            *count
+        };
+        if count < self.settings.failure_threshold.max(1) {
+            return;
+        }
+
+        self.failures.lock().unwrap().remove(host);
+        let mut learned = self.learned.lock().unwrap();
+        if !learned.insert(host.to_string()) {
+            return;
+        }
+        warn!("TLS handshake failed {} times for {}; tunnelling it without interception from now on", count, host);
+        let mut hosts: Vec<&String> = learned.iter().collect();
+        hosts.sort();
+        let content: String = hosts.into_iter().map(|h| format!("{}\n", h)).collect();
+        if let Err(e) = std::fs::write(&self.store_path, content) {
+            warn!("Failed to persist learned TLS passthrough hosts: {}", e);
+        }
+    }
+}
+
+/// True when the client aborted the handshake with an alert that means it
+/// rejected our certificate. Resets, timeouts and non-TLS bytes do not count.
+pub fn is_certificate_rejection(error: &std::io::Error) -> bool {
+    let Some(rustls::Error::AlertReceived(alert)) = error.get_ref().and_then(|e| e.downcast_ref::<rustls::Error>()) else {
+        return false;
+    };
+    matches!(
+        alert,
+        AlertDescription::BadCertificate | AlertDescription::UnknownCA | AlertDescription::CertificateUnknown
+    )
+}
@@ -1,71 +1,157 @@
 // File: crates/service/src/proxy.rs
 
+use crate::acl::{AccessControl, Listener};
+use crate::auth;
 use crate::certs::CertificateAuthority;
 use crate::cache::CacheManager;
 use crate::downloader;
+use crate::flows::{self, BodyError};
+use crate::har::{self, Replayed};
+use crate::listen;
+use crate::mapping;
+use crate::mock;
 use crate::management::{EVENT_BROADCASTER, WsEvent};
+use crate::passthrough::{self, PassthroughRegistry};
+use crate::proxy_protocol::ProxyProtocolAcceptor;
+use crate::upstream;
+use crate::rewrite::{self, TemplateVars};
 use crate::rules::RuleEngine;
+use crate::shutdown;
+use crate::throttle;
+use crate::pages::{self, PageContext, PageKind};
+use crate::socks;
+use crate::websocket;
 use anyhow::{Context, Result};
+use futures_util::{FutureExt, StreamExt};
 use hyper::server::conn::Http;
 use hyper::service::service_fn;
-use hyper::{upgrade, Body, Method, Request, Response, Uri};
-use sentiric_core::{Action, FlowEntry};
-use std::net::SocketAddr;
+use hyper::header::{HeaderMap, HeaderValue, AGE, PROXY_AUTHENTICATE, PROXY_AUTHORIZATION, VIA};
+use hyper::{upgrade, Body, Method, Request, Response, Uri, Version};
+use sentiric_core::{Action, CacheDecision, CacheHeaders, CacheOutcome, FlowEntry, MockSummary, PassthroughReason};
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpListener;
 use tokio_rustls::TlsAcceptor;
-use tracing::{error, info, warn, Instrument};
+use tracing::{debug, error, info, warn, Instrument};
 use uuid::Uuid;
 
+/// What the proxy knows about the client behind a request, carried into flows and rule matching.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ClientInfo {
+    /// Authenticated proxy user, if proxy authentication is enabled.
+    pub user: Option<String>,
+    pub ip: Option<IpAddr>,
+}
+
+/// Runs the HTTP proxy and, when enabled, the SOCKS5 listener on every bind address.
 pub async fn run_server(
-    addr: SocketAddr,
     ca: Arc<CertificateAuthority>,
     cache: Arc<CacheManager>,
+    passthrough: Arc<PassthroughRegistry>,
 ) -> Result<()> {
-    let listener = TcpListener::bind(addr).await?;
-    info!("🚀 Proxy server listening on http://{}", addr);
+    let settings = &crate::config::get().proxy;
+    let access = Arc::new(AccessControl::new(Listener::Proxy, &settings.access)?);
+    let proxy_protocol = Arc::new(ProxyProtocolAcceptor::new(&settings.proxy_protocol)?);
+    let mut servers = Vec::new();
+    for addr in listen::socket_addrs(&settings.bind_address, settings.port)? {
+        let listener = listen::bind_tcp(addr)?;
+        servers.push(run_http_proxy(listener, ca.clone(), cache.clone(), passthrough.clone(), access.clone(), proxy_protocol.clone()).boxed());
+    }
+    if settings.socks5.enabled {
+        for addr in listen::socket_addrs(&settings.bind_address, settings.socks5.port)? {
+            let listener = listen::bind_tcp(addr)?;
+            servers.push(socks::run_server(listener, ca.clone(), cache.clone(), passthrough.clone(), access.clone(), proxy_protocol.clone()).boxed());
+        }
+    }
+    futures_util::future::try_join_all(servers).await?;
+    Ok(())
+}
+
+async fn run_http_proxy(
+    listener: TcpListener,
+    ca: Arc<CertificateAuthority>,
+    cache: Arc<CacheManager>,
+    passthrough: Arc<PassthroughRegistry>,
+    access: Arc<AccessControl>,
+    proxy_protocol: Arc<ProxyProtocolAcceptor>,
+) -> Result<()> {
+    info!("🚀 Proxy server listening on http://{}", listener.local_addr()?);
 
     loop {
-        let (stream, client_addr) = listener.accept().await?;
+        let (mut stream, peer_addr) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = shutdown::requested() => return Ok(()),
+        };
         let ca_clone = ca.clone();
         let cache_clone = cache.clone();
+        let passthrough_clone = passthrough.clone();
+        let access = access.clone();
+        let proxy_protocol = proxy_protocol.clone();
 
-        let service = service_fn(move |req| {
-            let ca = ca_clone.clone();
-            let cache = cache_clone.clone();
-            proxy_service(req, ca, cache)
-        });
+        tokio::spawn(async move {
+            // PROXY başlığı yavaş gelebilir; kabul döngüsünü bekletmemek için burada okunur
+            let Some(client_addr) = proxy_protocol.client_addr(&mut stream, peer_addr).await else {
+                return;
+            };
+            if !access.check(client_addr.ip()) {
+                return;
+            }
+            let service = service_fn(move |req| {
+                let ca = ca_clone.clone();
+                let cache = cache_clone.clone();
+                let passthrough = passthrough_clone.clone();
+                proxy_service(req, ca, cache, passthrough, client_addr)
+            });
 
-        tokio::spawn(
             async move {
-                if let Err(err) = Http::new()
-                    .http1_only(true)
-                    .http1_keep_alive(true)
-                    .serve_connection(stream, service)
-                    .with_upgrades()
-                    .await
-                {
+                if let Err(err) = shutdown::serve_connection(Http::new().http1_only(true).http1_keep_alive(true), stream, service).await {
                     if !err.to_string().contains("connection reset") && !err.to_string().contains("unexpected end of file") {
                         warn!(cause = ?err, "Connection error");
                     }
                 }
             }
-            .instrument(tracing::info_span!("client", %client_addr)),
-        );
+            .instrument(tracing::info_span!("client", %client_addr))
+            .await
+        });
     }
 }
 
 async fn proxy_service(
-    req: Request<Body>,
+    mut req: Request<Body>,
     ca: Arc<CertificateAuthority>,
     cache: Arc<CacheManager>,
+    passthrough: Arc<PassthroughRegistry>,
+    client_addr: SocketAddr,
 ) -> Result<Response<Body>, hyper::Error> {
+    let cache_headers = &crate::config::get().proxy.cache_headers;
+    let client_info = ClientInfo {
+        ip: Some(client_addr.ip()),
+        user: if auth::is_enabled() {
+            match auth::authenticate_header(req.headers().get(PROXY_AUTHORIZATION)) {
+                Some(user) => Some(user),
+                None => return Ok(proxy_auth_required()),
+            }
+        } else {
+            None
+        },
+    };
+    // Kimlik bilgileri bu proxy içindir; akışlara ve origin'e ulaşmamalı
+    req.headers_mut().remove(PROXY_AUTHORIZATION);
+
     if Method::CONNECT == req.method() {
         if let Some(host) = req.uri().authority().map(|auth| auth.to_string()) {
+            let reason = passthrough_reason(req.uri().host().unwrap_or(&host), &client_info, &passthrough);
             tokio::spawn(async move {
+                let _guard = shutdown::track();
                 match upgrade::on(req).await {
                     Ok(upgraded) => {
-                        if let Err(e) = serve_https(upgraded, host, ca, cache).await {
+                        if let Some(reason) = reason {
+                            if let Err(e) = tunnel(upgraded, host, "TLS", Some(reason), client_info).await {
+                                error!(cause = ?e, "TLS passthrough error");
+                            }
+                        } else if let Err(e) = serve_https(upgraded, host, ca, cache, passthrough, client_info, cache_headers).await {
                              if !e.to_string().contains("TLS handshake failed") {
                                 error!(cause = ?e, "HTTPS tunnel error");
                             }
@@ -81,43 +167,268 @@ async fn proxy_service(
             Ok(resp)
         }
     } else {
-        serve_http(req, cache, false).await
+        serve_http(req, cache, false, client_info, cache_headers).await
     }
 }
 
-async fn serve_http(
+fn proxy_auth_required() -> Response<Body> {
+    let mut resp = Response::new(Body::from("Proxy authentication required"));
+    *resp.status_mut() = http::StatusCode::PROXY_AUTHENTICATION_REQUIRED;
+    if let Ok(challenge) = format!("Basic realm=\"{}\"", auth::realm()).parse() {
+        resp.headers_mut().insert(PROXY_AUTHENTICATE, challenge);
+    }
+    resp
+}
+
+/// Decides whether a TLS connection to `host` must be spliced instead of intercepted.
+pub(crate) fn passthrough_reason(
+    host: &str,
+    client_info: &ClientInfo,
+    passthrough: &PassthroughRegistry,
+) -> Option<PassthroughReason> {
+    let rule_engine = RuleEngine::new(crate::config::get().rules.clone());
+    if let Some(rule) = rule_engine.match_rule(&format!("https://{}/", host), client_info.user.as_deref()) {
+        if rule.action == Action::Tunnel {
+            return Some(PassthroughReason::Rule { name: rule.name.clone() });
+        }
+    }
+    passthrough.is_learned(host).then_some(PassthroughReason::Learned)
+}
+
+/// Splices the client connection to the origin byte for byte, without
+/// terminating TLS or parsing the protocol spoken inside.
+pub(crate) async fn tunnel<IO>(
+    mut client: IO,
+    host: String,
+    protocol: &str,
+    reason: Option<PassthroughReason>,
+    client_info: ClientInfo,
+) -> Result<()>
+where
+    IO: AsyncRead + AsyncWrite + Unpin,
+{
+    info!("[TUNNEL] {} ({}, {:?})", host, protocol, reason);
+    let started = Instant::now();
+    let mut flow = FlowEntry {
+        id: Uuid::new_v4().to_string(),
+        method: Method::CONNECT.to_string(),
+        uri: host.clone(),
+        status_code: 200,
+        protocol: protocol.to_string(),
+        tls_passthrough: reason,
+        user: client_info.user,
+        client_ip: client_info.ip.map(|ip| ip.to_canonical()),
+        started_at_ms: flows::now_ms(),
+        ..FlowEntry::default()
+    };
+    let _ = EVENT_BROADCASTER.send(WsEvent::FlowUpdated { flow: Box::new(flow.clone()) });
+
+    let result = async {
+        let connect_timeout = Duration::from_secs(crate::config::get().downloader.connect_timeout_secs);
+        let mut server = tokio::time::timeout(connect_timeout, upstream::connect(&host))
+            .await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "connect timeout"))?
+            .context("Failed to connect to origin")?;
+        let (_, from_server) = tokio::select! {
+            copied = tokio::io::copy_bidirectional(&mut client, &mut server) => copied?,
+            // Kapanışta ömrü belirsiz tünelleri beklemek yerine kes.
+            _ = shutdown::requested() => (0, 0),
+        };
+        Ok::<_, anyhow::Error>(from_server)
+    }
+    .await;
+
+    match &result {
+        Ok(from_server) => flow.response_size_bytes = *from_server,
+        Err(e) => {
+            flow.status_code = http::StatusCode::BAD_GATEWAY.as_u16();
+            flow.error = Some(downloader::failure_reason(e.as_ref()));
+        }
+    }
+    flow.timings.total_ms = Some(flows::ms(started.elapsed()));
+    let _ = EVENT_BROADCASTER.send(WsEvent::FlowUpdated { flow: Box::new(flow) });
+    result.map(|_| ())
+}
+
+pub(crate) async fn serve_http(
     mut req: Request<Body>,
     cache: Arc<CacheManager>,
     is_https: bool,
+    client_info: ClientInfo,
+    cache_headers: &'static CacheHeaders,
 ) -> Result<Response<Body>, hyper::Error> {
+    let started = Instant::now();
     let uri_string = if is_https {
         req.uri().to_string()
     } else {
         let host = req.headers().get(hyper::header::HOST).and_then(|h| h.to_str().ok()).unwrap_or_default();
         format!("http://{}{}", host, req.uri().path_and_query().map(|p| p.as_str()).unwrap_or("/"))
     };
-    
-    *req.uri_mut() = uri_string.parse().unwrap();
+
+    // Şeffaf dinleyiciler her türlü trafiği alır; Host başlığı olmayan HTTP/1.0 istekleri de gelir
+    match uri_string.parse::<Uri>() {
+        Ok(uri) if uri.host().is_some_and(|host| !host.is_empty()) => *req.uri_mut() = uri,
+        _ => {
+            debug!("Rejecting request without a usable target: {:?}", uri_string);
+            let context = PageContext {
+                status: http::StatusCode::BAD_REQUEST,
+                rule: None,
+                url: &uri_string,
+                flow_id: &Uuid::new_v4().to_string(),
+                detail: Some("The request has no valid Host header or target URI."),
+                json: pages::wants_json(req.headers()),
+            };
+            return Ok(pages::render(PageKind::BadRequest, context));
+        }
+    }
+    let mut flow = flows::start(&req, uri_string.clone(), &client_info);
+    let request_body = flows::capture_request(&mut req, &crate::config::get().flows);
+
+    // HAR kaydı, istemcinin gönderdiği isteği ve aldığı yanıtı tutar
+    let recording = if websocket::is_upgrade_request(&req) { None } else { har::record_request(&mut req, &uri_string, &flow.id) };
+    let response = handle_http(req, uri_string, &mut flow, cache, client_info, cache_headers).await?;
+    // Açılan WebSocket tünelleri akışı kendileri günceller
+    if flow.websocket.is_some() {
+        return Ok(response);
+    }
+    let response = flows::track(response, flow, started, request_body);
+    Ok(match recording {
+        Some(entry) => entry.record_response(response),
+        None => response,
+    })
+}
+
+/// Applies replay, rules and the cache to a request whose URI is already absolute,
+/// noting in `flow` how the response came about.
+async fn handle_http(
+    mut req: Request<Body>,
+    uri_string: String,
+    flow: &mut FlowEntry,
+    cache: Arc<CacheManager>,
+    client_info: ClientInfo,
+    cache_headers: &'static CacheHeaders,
+) -> Result<Response<Body>, hyper::Error> {
+    // Yönlendirici isteğin sürümünü değiştirdiği için istemcinin protokolünü şimdiden al.
+    let client_version = req.version();
 
     let rule_engine = RuleEngine::new(crate::config::get().rules.clone());
-    let action = rule_engine.match_action(&uri_string);
+    let rule = rule_engine.match_rule(&uri_string, client_info.user.as_deref());
+    let rule_name = rule.map(|rule| rule.name.as_str());
+    let json = pages::wants_json(req.headers());
+    let action = rule.map(|rule| rule.action.clone()).unwrap_or(Action::Allow);
+    let throttle_rule = match (rule, &action) {
+        (Some(rule), Action::Throttle(rate)) => Some((rule.name.clone(), *rate)),
+        _ => None,
+    };
+    let header_rewrite = match &action {
+        Action::RewriteHeaders(rewrite) => Some(rewrite),
+        _ => None,
+    };
+    let domain = req.uri().host().map(str::to_string);
+    let method = req.method().clone();
+    let rewrite_vars = TemplateVars {
+        client_ip: client_info.ip,
+        user: client_info.user.as_deref(),
+        host: domain.as_deref().unwrap_or_default(),
+        method: method.as_str(),
+        url: &uri_string,
+    };
+    let throttle_scope = |is_hit| throttle::Scope {
+        client: client_info.ip,
+        domain: domain.as_deref(),
+        rule: throttle_rule.as_ref().map(|(name, rate)| (name.as_str(), *rate)),
+        is_hit,
+    };
 
+    flow.rule = rule_name.map(str::to_string);
+
+    // Yüklenen bir HAR dosyası engellenen adresleri sunamamalı
     if action == Action::Block {
         info!("[BLOCK] {}", uri_string);
-        let mut resp = Response::new(Body::from("Blocked by Sentiric Traffic Cache rule."));
-        *resp.status_mut() = http::StatusCode::FORBIDDEN;
-        return Ok(resp);
+        let status = http::StatusCode::FORBIDDEN;
+        flow.cache = Some(bypass("Blocked by rule"));
+        let context = PageContext { status, rule: rule_name, url: &uri_string, flow_id: &flow.id, detail: None, json };
+        return Ok(pages::render(PageKind::Block, context));
     }
 
-    if action == Action::BypassCache {
+    match har::replay(req.method(), &uri_string) {
+        Replayed::Response(response) => {
+            info!("[REPLAY] {}", uri_string);
+            flow.replayed = true;
+            flow.cache = Some(bypass("Replayed from HAR file"));
+            return Ok(response);
+        }
+        Replayed::Missing => {
+            info!("[REPLAY MISS] {}", uri_string);
+            let status = http::StatusCode::BAD_GATEWAY;
+            flow.error = Some("Not in the replayed HAR file".to_string());
+            flow.cache = Some(bypass("Strict HAR replay"));
+            let context = PageContext { status, rule: None, url: &uri_string, flow_id: &flow.id, detail: None, json };
+            return Ok(pages::render(PageKind::Unrecorded, context));
+        }
+        Replayed::PassThrough => {}
+    }
+
+    // Origin'e giden istek ve önbellek kararı yeniden yazılmış başlıkları görür
+    if let Some(rewrite) = header_rewrite {
+        rewrite::apply(&rewrite.request, req.headers_mut(), &rewrite_vars);
+    }
+
+    if let (Action::Mock(response), Some(rule)) = (&action, rule) {
+        let (response, failure_injected) = mock::respond(response).await;
+        info!("[MOCK] {} -> {}{}", uri_string, response.status(), if failure_injected { " (injected failure)" } else { "" });
+        flow.mock = Some(MockSummary { rule: rule.name.clone(), failure_injected });
+        flow.cache = Some(bypass("Answered by mock rule"));
+        return Ok(response);
+    }
+
+    if let Action::MapLocal(target) = &action {
+        info!("[MAP-LOCAL] {} -> {}", uri_string, target);
+        flow.cache = Some(bypass("Served from a local file"));
+        return Ok(match mapping::serve_local(target, req.uri(), req.method()).await {
+            Ok(resp) => resp,
+            Err((status, reason)) => {
+                let context = PageContext { status, rule: rule_name, url: &uri_string, flow_id: &flow.id, detail: Some(reason), json };
+                pages::render(PageKind::LocalFile, context)
+            }
+        });
+    }
+
+    // Akışlarda özgün URL görünür; yalnızca bağlanılan hedef değişir
+    if let Action::MapRemote(target) = &action {
+        match mapping::remote_uri(target, req.uri()) {
+            Ok(mapped) => {
+                info!("[MAP-REMOTE] {} -> {}", uri_string, mapped);
+                flow.cache = Some(bypass(&format!("Mapped to {}", mapped)));
+                *req.uri_mut() = mapped;
+            }
+            Err(e) => return Ok(upstream_failure(flow, &e, rule_name, json)),
+        }
+    }
+
+    if websocket::is_upgrade_request(&req) {
+        flow.cache.get_or_insert_with(|| bypass("WebSocket upgrade"));
+        return match websocket::serve(req, flow).await {
+            Ok(resp) => Ok(resp),
+            Err(e) => {
+                error!("WebSocket forward error: {:#}", e);
+                Ok(upstream_failure(flow, &e, rule_name, json))
+            }
+        };
+    }
+
+    if matches!(action, Action::BypassCache | Action::MapRemote(_)) {
         info!("[BYPASS] {}", uri_string);
+        flow.cache.get_or_insert_with(|| bypass("Rule bypasses the cache"));
         return match downloader::forward_request(req).await {
-            Ok(resp) => Ok(resp),
+            Ok(mut resp) => {
+                flows::record_upstream(flow, &resp);
+                add_cache_headers(resp.headers_mut(), cache_headers, CacheOutcome::Bypass, client_version, None);
+                Ok(resp.map(|body| throttle::limit(body, throttle_scope(false))))
+            }
             Err(e) => {
-                error!("Bypass forward error: {}", e);
-                let mut resp = Response::new(Body::from("Upstream request failed"));
-                *resp.status_mut() = http::StatusCode::BAD_GATEWAY;
-                Ok(resp)
+                error!("Bypass forward error: {:#}", e);
+                Ok(upstream_failure(flow, &e, rule_name, json))
             }
         };
     }
@@ -130,55 +441,48 @@ async fn serve_http(
         info!("[HIT] {}", uri_string);
         
         // Header'ları al ve response'u oluştur
-        let mut response = Response::new(cached_body);
+        let mut response = Response::new(throttle::limit(cached_body, throttle_scope(true)));
         
         // Cache'ten header bilgilerini al
-        if let Some((content_encoding, content_type)) = cache.get_headers(&cache_key).await {
-            if !content_encoding.is_empty() {
+        let mut age = None;
+        if let Some(stored) = cache.get_headers(&cache_key).await {
+            if let Some(content_encoding) = stored.content_encoding.filter(|v| !v.is_empty()) {
                 response.headers_mut().insert("content-encoding", content_encoding.parse().unwrap());
             }
-            if !content_type.is_empty() {
+            if let Some(content_type) = stored.content_type.filter(|v| !v.is_empty()) {
                 response.headers_mut().insert("content-type", content_type.parse().unwrap());
             }
+            age = stored.age;
         }
         
         // Vary header'ını garanti et
         response.headers_mut().insert("vary", "accept-encoding".parse().unwrap());
-        
-        let _ = EVENT_BROADCASTER.send(WsEvent::FlowUpdated {
-            flow: FlowEntry {
-                id: Uuid::new_v4().to_string(),
-                method: req.method().to_string(),
-                uri: uri_string,
-                status_code: 200,
-                response_size_bytes: 0,
-                is_hit: true,
-            }
-        });
+        if let Some(rewrite) = header_rewrite {
+            rewrite::apply(&rewrite.response, response.headers_mut(), &rewrite_vars);
+        }
+        add_cache_headers(response.headers_mut(), cache_headers, CacheOutcome::Hit, client_version, age);
+
+        flow.is_hit = true;
+        let reason = match age {
+            Some(age) => format!("Stored {}s ago", age.as_secs()),
+            None => "Stored response".to_string(),
+        };
+        flow.cache = Some(CacheDecision { outcome: CacheOutcome::Hit, reason });
         return Ok(response);
     }
 
     info!("[MISS] {}", uri_string);
+    flow.cache = Some(CacheDecision { outcome: CacheOutcome::Miss, reason: "Not in cache".to_string() });
 
+    let upstream_started = Instant::now();
     match downloader::forward_request(req).await {
         Ok(mut response) => {
-            let status_code = response.status().as_u16();
-            let content_length = response.headers()
-                .get(hyper::header::CONTENT_LENGTH)
-                .and_then(|v| v.to_str().ok())
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(0);
-            
-            let _ = EVENT_BROADCASTER.send(WsEvent::FlowUpdated {
-                flow: FlowEntry {
-                    id: Uuid::new_v4().to_string(),
-                    method: "GET".to_string(),
-                    uri: uri_string.clone(),
-                    status_code,
-                    response_size_bytes: content_length,
-                    is_hit: false,
-                }
-            });
+            let upstream_latency = upstream_started.elapsed();
+            flows::record_upstream(flow, &response);
+            // Saklanan Content-Type/Encoding da düzeltilmiş değerler olsun
+            if let Some(rewrite) = header_rewrite {
+                rewrite::apply(&rewrite.response, response.headers_mut(), &rewrite_vars);
+            }
 
             // Header'ları al
             let content_encoding = response.headers()
@@ -191,33 +495,136 @@ async fn serve_http(
                 .and_then(|h| h.to_str().ok())
                 .map(|s| s.to_string());
 
-            let body_stream = std::mem::replace(response.body_mut(), Body::empty());
-            if let Ok(body_for_client) = cache.put_stream(cache_key, body_stream, content_encoding, content_type).await {
+            add_cache_headers(response.headers_mut(), cache_headers, CacheOutcome::Miss, client_version, None);
+            let body_error = BodyError::default();
+            response.extensions_mut().insert(body_error.clone());
+            let body_stream = report_body_errors(std::mem::replace(response.body_mut(), Body::empty()), body_error);
+            let body_stream = throttle::limit(body_stream, throttle_scope(false));
+            if let Ok(body_for_client) = cache.put_stream(cache_key, body_stream, content_encoding, content_type, upstream_latency).await {
                 *response.body_mut() = body_for_client;
             }
             Ok(response)
         }
         Err(e) => {
-            error!("Forward error: {}", e);
-            let mut resp = Response::new(Body::from("Upstream request failed"));
-            *resp.status_mut() = http::StatusCode::BAD_GATEWAY;
-            Ok(resp)
+            error!("Forward error: {:#}", e);
+            Ok(upstream_failure(flow, &e, rule_name, json))
         }
     }
 }
 
-async fn serve_https(
-    upgraded: upgrade::Upgraded,
+fn bypass(reason: &str) -> CacheDecision {
+    CacheDecision { outcome: CacheOutcome::Bypass, reason: reason.to_string() }
+}
+
+/// Adds the RFC 9211 `Cache-Status`, `Age` and `Via` headers and, if configured, `X-Cache`.
+fn add_cache_headers(
+    headers: &mut HeaderMap,
+    settings: &CacheHeaders,
+    outcome: CacheOutcome,
+    client_version: Version,
+    age: Option<Duration>,
+) {
+    if settings.x_cache {
+        let value = match outcome {
+            CacheOutcome::Hit => "HIT",
+            CacheOutcome::Miss => "MISS",
+            CacheOutcome::Bypass => "BYPASS",
+        };
+        headers.insert("x-cache", HeaderValue::from_static(value));
+    }
+    if !settings.enabled {
+        return;
+    }
+
+    let status = match outcome {
+        CacheOutcome::Hit => "hit",
+        CacheOutcome::Miss => "fwd=miss",
+        CacheOutcome::Bypass => "fwd=bypass",
+    };
+    // Origin'e en yakın önbellek listenin başında olur; kendi üyemizi sona ekliyoruz
+    if let Ok(value) = HeaderValue::from_str(&format!("{}; {}", settings.name, status)) {
+        headers.append("cache-status", value);
+    }
+    if let Some(age) = age {
+        headers.insert(AGE, HeaderValue::from(age.as_secs()));
+    }
+    let received_protocol = match client_version {
+        Version::HTTP_09 => "0.9",
+        Version::HTTP_10 => "1.0",
+        Version::HTTP_2 => "2",
+        Version::HTTP_3 => "3",
+        _ => "1.1",
+    };
+    if let Ok(value) = HeaderValue::from_str(&format!("{} {}", received_protocol, settings.name)) {
+        headers.append(VIA, value);
+    }
+}
+
+/// Notes the failure reason in `flow` and answers the client with the matching error page.
+fn upstream_failure(flow: &mut FlowEntry, err: &anyhow::Error, rule: Option<&str>, json: bool) -> Response<Body> {
+    let kind = downloader::failure_kind(err.as_ref());
+    let reason = downloader::failure_reason(err.as_ref());
+    flow.error = Some(reason.clone());
+
+    pages::render(
+        PageKind::for_failure(kind),
+        PageContext {
+            status: kind.status(),
+            rule,
+            url: &flow.uri,
+            flow_id: &flow.id,
+            detail: Some(&reason),
+            json,
+        },
+    )
+}
+
+/// Notes the failure reason if the upstream body breaks off mid-transfer.
+fn report_body_errors(body: Body, error: BodyError) -> Body {
+    Body::wrap_stream(body.map(move |chunk| {
+        if let Err(e) = &chunk {
+            error.set(downloader::failure_reason(e));
+        }
+        chunk
+    }))
+}
+
+/// Terminates TLS on `client` with a leaf certificate for `host` and serves the
+/// decrypted requests through `serve_http`. `host` must include the port.
+pub(crate) async fn serve_https<IO>(
+    client: IO,
     host: String,
     ca: Arc<CertificateAuthority>,
     cache: Arc<CacheManager>,
-) -> Result<()> {
-    let server_config = ca.get_server_config(host.split(':').next().unwrap_or(&host))?;
-    let stream = TlsAcceptor::from(server_config).accept(upgraded).await.context("TLS handshake failed")?;
+    passthrough: Arc<PassthroughRegistry>,
+    client_info: ClientInfo,
+    cache_headers: &'static CacheHeaders,
+) -> Result<()>
+where
+    IO: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let hostname = host.split(':').next().unwrap_or(&host);
+    let server_config = ca.get_server_config(hostname)?;
+    let stream = match TlsAcceptor::from(server_config).accept(client).await {
+        Ok(stream) => {
+            passthrough.record_handshake_success(hostname);
+            stream
+        }
+        Err(e) => {
+            // Sertifika sabitleme yapan istemciler el sıkışmayı burada reddeder.
+            if passthrough::is_certificate_rejection(&e) {
+                passthrough.record_handshake_failure(hostname);
+            }
+            return Err(e).context("TLS handshake failed");
+        }
+    };
+    let is_h2 = stream.get_ref().1.alpn_protocol() == Some(b"h2".as_slice());
+    debug!("TLS established for {} (ALPN: {})", host, if is_h2 { "h2" } else { "http/1.1" });
 
     let service = service_fn(move |mut req: Request<Body>| {
         let cache = cache.clone();
         let host = host.clone();
+        let client_info = client_info.clone();
         async move {
             let authority = host.parse::<http::uri::Authority>().unwrap();
             let uri = Uri::builder()
@@ -227,12 +634,11 @@ async fn serve_https(
                 .build()
                 .unwrap();
             *req.uri_mut() = uri;
-            serve_http(req, cache, true).await
+            serve_http(req, cache, true, client_info, cache_headers).await
         }
     });
 
-    Http::new()
-        .serve_connection(stream, service)
+    shutdown::serve_connection(Http::new().http1_only(!is_h2).http2_only(is_h2), stream, service)
         .await
         .context("Error serving HTTPS connection")
 }
\ No newline at end of file
@@ -0,0 +1,249 @@
+// File: crates/service/src/proxy_protocol.rs
+
+use crate::acl;
+use anyhow::{bail, Context, Result};
+use ipnet::IpNet;
+use sentiric_core::ProxyProtocol;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tracing::{debug, warn};
+
+// Belirtim: https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+/// Longest v1 header allowed by the specification, including CRLF.
+const V1_MAX_LEN: usize = 107;
+
+/// PROXY protocol (v1 and v2) headers from load balancers such as HAProxy or AWS NLB:
+/// decides which peers must send one and extracts the client address from it.
+#[derive(Debug)]
+pub struct ProxyProtocolAcceptor {
+    enabled: bool,
+    trusted: Vec<IpNet>,
+    timeout: Duration,
+}
+
+impl ProxyProtocolAcceptor {
+    pub fn new(settings: &ProxyProtocol) -> Result<Self> {
+        // Güvenilen eş yoksa herkes sahte bir başlıkla istemci adresini seçebilirdi
+        if settings.enabled && settings.trusted_proxies.is_empty() {
+            bail!("proxy_protocol is enabled but trusted_proxies is empty; list the load balancers that send the header");
+        }
+        Ok(Self {
+            enabled: settings.enabled,
+            trusted: acl::parse_networks(&settings.trusted_proxies).context("Invalid proxy_protocol.trusted_proxies")?,
+            timeout: Duration::from_secs(settings.header_timeout_secs),
+        })
+    }
+
+    /// The client behind a freshly accepted connection from `peer`. Trusted peers must
+    /// start with a PROXY header; `None` means the connection should be dropped.
+    pub async fn client_addr<S: AsyncRead + Unpin>(&self, stream: &mut S, peer: SocketAddr) -> Option<SocketAddr> {
+        let peer_ip = peer.ip().to_canonical();
+        if !self.enabled || !self.trusted.iter().any(|net| net.contains(&peer_ip)) {
+            return Some(peer);
+        }
+        match tokio::time::timeout(self.timeout, read_header(stream)).await {
+            Ok(Ok(Some(addr))) => {
+                debug!("PROXY protocol header from {} names client {}", peer, addr);
+                Some(addr)
+            }
+            // LOCAL / UNKNOWN: yük dengeleyicinin kendi sağlık kontrolü
+            Ok(Ok(None)) => Some(peer),
+            Ok(Err(e)) => {
+                warn!("Dropping connection from {}: {:#}", peer, e);
+                None
+            }
+            Err(_) => {
+                warn!("Dropping connection from {}: no PROXY protocol header within {:?}", peer, self.timeout);
+                None
+            }
+        }
+    }
+}
+
+/// Reads a v1 or v2 header, leaving the stream at the first byte of client data.
+/// Returns `None` for headers that carry no address (`LOCAL`, `UNKNOWN`, non-IP families).
+pub async fn read_header<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Option<SocketAddr>> {
+    let mut prefix = [0u8; 12];
+    stream.read_exact(&mut prefix).await.context("Connection closed before PROXY protocol header")?;
+    if prefix == V2_SIGNATURE {
+        read_v2(stream).await
+    } else if prefix.starts_with(b"PROXY ") {
+        read_v1(stream, &prefix).await
+    } else {
+        bail!("Missing PROXY protocol header")
+    }
+}
+
+async fn read_v1<S: AsyncRead + Unpin>(stream: &mut S, prefix: &[u8]) -> Result<Option<SocketAddr>> {
+    // Sonraki baytlar istemci verisi olduğundan satır sonuna kadar tek tek okunur
+    let mut line = prefix.to_vec();
+    while !line.ends_with(b"\r\n") {
+        if line.len() >= V1_MAX_LEN {
+            bail!("PROXY protocol v1 header is too long");
+        }
+        line.push(stream.read_u8().await.context("Connection closed inside PROXY protocol header")?);
+    }
+    let line = std::str::from_utf8(&line[..line.len() - 2]).context("PROXY protocol v1 header is not ASCII")?;
+    let fields: Vec<&str> = line.split(' ').collect();
+    match fields.as_slice() {
+        ["PROXY", "UNKNOWN", ..] => Ok(None),
+        ["PROXY", family @ ("TCP4" | "TCP6"), source, _destination, source_port, _destination_port] => {
+            let ip: IpAddr = source.parse().with_context(|| format!("Invalid source address '{}'", source))?;
+            if ip.is_ipv4() != (*family == "TCP4") {
+                bail!("Source address '{}' does not match {}", source, family);
+            }
+            let port = source_port.parse().with_context(|| format!("Invalid source port '{}'", source_port))?;
+            Ok(Some(SocketAddr::new(ip, port)))
+        }
+        _ => bail!("Malformed PROXY protocol v1 header '{}'", line),
+    }
+}
+
+async fn read_v2<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Option<SocketAddr>> {
+    let mut fixed = [0u8; 4];
+    stream.read_exact(&mut fixed).await.context("Connection closed inside PROXY protocol header")?;
+    let [version_command, family, len_hi, len_lo] = fixed;
+    if version_command >> 4 != 2 {
+        bail!("Unsupported PROXY protocol version {}", version_command >> 4);
+    }
+    // Adres bloğu ve TLV'ler, LOCAL komutunda da okunup atılmalı
+    let mut payload = vec![0u8; u16::from_be_bytes([len_hi, len_lo]) as usize];
+    stream.read_exact(&mut payload).await.context("Connection closed inside PROXY protocol header")?;
+
+    match version_command & 0x0f {
+        0x0 => return Ok(None),
+        0x1 => {}
+        command => bail!("Unknown PROXY protocol v2 command {:#x}", command),
+    }
+    let addr = match family >> 4 {
+        0x1 if payload.len() >= 12 => {
+            let ip = Ipv4Addr::new(payload[0], payload[1], payload[2], payload[3]);
+            SocketAddr::new(IpAddr::V4(ip), u16::from_be_bytes([payload[8], payload[9]]))
+        }
+        0x2 if payload.len() >= 36 => {
+            let octets: [u8; 16] = payload[..16].try_into().expect("slice has 16 bytes");
+            SocketAddr::new(IpAddr::V6(Ipv6Addr::from(octets)), u16::from_be_bytes([payload[32], payload[33]]))
+        }
+        0x1 | 0x2 => bail!("Truncated PROXY protocol v2 address block"),
+        // AF_UNSPEC / AF_UNIX: taşınan bir IP adresi yok
+        _ => return Ok(None),
+    };
+    Ok(Some(addr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn parse(bytes: &[u8]) -> (Result<Option<SocketAddr>>, Vec<u8>) {
+        let mut stream = bytes;
+        let result = read_header(&mut stream).await;
+        (result, stream.to_vec())
+    }
+
+    fn v2(command: u8, family: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = V2_SIGNATURE.to_vec();
+        out.push(0x20 | command);
+        out.push(family);
+        out.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        out.extend_from_slice(payload);
+        out
+    }
+
+    #[tokio::test]
+    async fn v1_tcp4_leaves_client_data() {
+        let (addr, rest) = parse(b"PROXY TCP4 192.0.2.10 198.51.100.1 51234 3128\r\nGET / HTTP/1.1\r\n").await;
+        assert_eq!(addr.unwrap(), Some("192.0.2.10:51234".parse().unwrap()));
+        assert_eq!(rest, b"GET / HTTP/1.1\r\n");
+    }
+
+    #[tokio::test]
+    async fn v1_tcp6() {
+        let (addr, _) = parse(b"PROXY TCP6 2001:db8::1 2001:db8::2 40000 443\r\n").await;
+        assert_eq!(addr.unwrap(), Some("[2001:db8::1]:40000".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn v1_unknown_has_no_address() {
+        let (addr, rest) = parse(b"PROXY UNKNOWN\r\nhello").await;
+        assert_eq!(addr.unwrap(), None);
+        assert_eq!(rest, b"hello");
+    }
+
+    #[tokio::test]
+    async fn v1_rejects_family_mismatch() {
+        let (addr, _) = parse(b"PROXY TCP4 2001:db8::1 2001:db8::2 40000 443\r\n").await;
+        assert!(addr.is_err());
+    }
+
+    #[tokio::test]
+    async fn v1_rejects_truncated_and_over_long_headers() {
+        assert!(parse(b"PROXY TCP4 192.0.2.10 198.51").await.0.is_err());
+        let mut long = b"PROXY TCP4 ".to_vec();
+        long.extend_from_slice(&[b'1'; V1_MAX_LEN]);
+        long.extend_from_slice(b"\r\n");
+        assert!(parse(&long).await.0.is_err());
+    }
+
+    #[tokio::test]
+    async fn v2_tcp4_skips_tlvs() {
+        let mut payload = vec![192, 0, 2, 10, 198, 51, 100, 1];
+        payload.extend_from_slice(&51234u16.to_be_bytes());
+        payload.extend_from_slice(&3128u16.to_be_bytes());
+        payload.extend_from_slice(&[0x04, 0x00, 0x02, 0xAA, 0xBB]);
+        let mut bytes = v2(0x1, 0x11, &payload);
+        bytes.extend_from_slice(b"data");
+        let (addr, rest) = parse(&bytes).await;
+        assert_eq!(addr.unwrap(), Some("192.0.2.10:51234".parse().unwrap()));
+        assert_eq!(rest, b"data");
+    }
+
+    #[tokio::test]
+    async fn v2_tcp6() {
+        let source: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let destination: Ipv6Addr = "2001:db8::2".parse().unwrap();
+        let mut payload = source.octets().to_vec();
+        payload.extend_from_slice(&destination.octets());
+        payload.extend_from_slice(&40000u16.to_be_bytes());
+        payload.extend_from_slice(&443u16.to_be_bytes());
+        let (addr, _) = parse(&v2(0x1, 0x21, &payload)).await;
+        assert_eq!(addr.unwrap(), Some("[2001:db8::1]:40000".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn v2_local_consumes_payload() {
+        let mut bytes = v2(0x0, 0x11, &[0; 12]);
+        bytes.extend_from_slice(b"health");
+        let (addr, rest) = parse(&bytes).await;
+        assert_eq!(addr.unwrap(), None);
+        assert_eq!(rest, b"health");
+    }
+
+    #[tokio::test]
+    async fn v2_unspec_family_has_no_address() {
+        let (addr, _) = parse(&v2(0x1, 0x00, &[])).await;
+        assert_eq!(addr.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn v2_rejects_truncated_headers() {
+        assert!(parse(&v2(0x1, 0x11, &[192, 0, 2, 10])).await.0.is_err());
+        let mut bytes = v2(0x1, 0x11, &[0; 12]);
+        bytes.truncate(bytes.len() - 4);
+        assert!(parse(&bytes).await.0.is_err());
+        assert!(parse(&V2_SIGNATURE[..8]).await.0.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_header() {
+        assert!(parse(b"GET / HTTP/1.1\r\nHost: x\r\n\r\n").await.0.is_err());
+    }
+
+    #[test]
+    fn enabled_without_trusted_proxies_is_rejected() {
+        let settings = ProxyProtocol { enabled: true, ..ProxyProtocol::default() };
+        assert!(ProxyProtocolAcceptor::new(&settings).is_err());
+    }
+}
@@ -0,0 +1,260 @@
+// File: crates/service/src/resolver.rs
+
+use crate::listen;
+use anyhow::{Context, Result};
+use hyper::client::connect::dns::Name;
+use hyper::service::Service;
+use sentiric_core::Settings;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::OnceLock;
+use std::task::{self, Poll};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+use trust_dns_resolver::config::{LookupIpStrategy, NameServerConfigGroup, ResolverConfig};
+use trust_dns_resolver::system_conf::read_system_conf;
+use trust_dns_resolver::TokioAsyncResolver;
+use wildmatch::WildMatch;
+
+// Sistem çözümleyicisi bizim DNS sunucumuza dönüp proxy'nin kendisine bağlanmasına yol açabilir
+static RESOLVER: OnceLock<UpstreamResolver> = OnceLock::new();
+
+/// An upstream address that is actually one of our own listeners.
+#[derive(Debug)]
+pub struct LoopDetected {
+    pub host: String,
+    pub addr: SocketAddr,
+}
+
+impl fmt::Display for LoopDetected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} resolves to this proxy's own listener {}", self.host, self.addr)
+    }
+}
+
+impl std::error::Error for LoopDetected {}
+
+pub struct UpstreamResolver {
+    inner: TokioAsyncResolver,
+    hosts: Vec<(WildMatch, Vec<IpAddr>)>,
+    listeners: Vec<SocketAddr>,
+    /// The address our DNS server hands out, i.e. this machine as seen by clients.
+    self_ip: Option<IpAddr>,
+}
+
+pub fn init(settings: &Settings) -> Result<()> {
+    let resolver = UpstreamResolver::new(settings)?;
+    RESOLVER.set(resolver).map_err(|_| anyhow::anyhow!("Resolver already initialized"))?;
+    Ok(())
+}
+
+pub fn get() -> &'static UpstreamResolver {
+    RESOLVER.get().expect("Resolver is not initialized")
+}
+
+impl UpstreamResolver {
+    fn new(settings: &Settings) -> Result<Self> {
+        let (config, mut opts) = if settings.resolver.nameservers.is_empty() {
+            let (config, opts) = read_system_conf().context("Failed to read system resolver configuration")?;
+            if settings.dns.enabled && config.name_servers().iter().any(|ns| ns.socket_addr.port() == settings.dns.port) {
+                warn!("The system resolver may point at our own DNS server; set resolver.nameservers to avoid loops.");
+            }
+            (config, opts)
+        } else {
+            let mut group = NameServerConfigGroup::new();
+            for entry in &settings.resolver.nameservers {
+                let addr = entry
+                    .parse::<SocketAddr>()
+                    .or_else(|_| entry.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+                    .with_context(|| format!("Invalid resolver nameserver '{}'", entry))?;
+                group.merge(NameServerConfigGroup::from_ips_clear(&[addr.ip()], addr.port(), true));
+            }
+            info!("Upstream resolver using nameservers: {}", settings.resolver.nameservers.join(", "));
+            (ResolverConfig::from_parts(None, vec![], group), Default::default())
+        };
+        opts.cache_size = settings.resolver.cache_size;
+        // Happy eyeballs için her iki aileyi de sorgula
+        opts.ip_strategy = LookupIpStrategy::Ipv4AndIpv6;
+
+        // Yönetim arayüzü proxy üzerinden açılabilir; döngü yalnızca proxy dinleyicilerinde oluşur
+        let mut listeners = listen::socket_addrs(&settings.proxy.bind_address, settings.proxy.port)?;
+        if settings.proxy.socks5.enabled {
+            listeners.extend(listen::socket_addrs(&settings.proxy.bind_address, settings.proxy.socks5.port)?);
+        }
+        if settings.transparent.enabled {
+            listeners.extend(listen::socket_addrs(&settings.transparent.bind_address, settings.transparent.http_port)?);
+            listeners.extend(listen::socket_addrs(&settings.transparent.bind_address, settings.transparent.https_port)?);
+        }
+
+        Ok(Self {
+            inner: TokioAsyncResolver::tokio(config, opts),
+            hosts: settings
+                .resolver
+                .hosts
+                .iter()
+                .map(|h| (WildMatch::new(&h.domain), h.addresses.clone()))
+                .collect(),
+            listeners,
+            self_ip: settings.dns.enabled.then_some(settings.dns.response_ip),
+        })
+    }
+
+    /// Resolves `host`, consulting the overrides first. IP literals are returned as-is.
+    pub async fn lookup(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        let host = host.trim_matches(|c| c == '[' || c == ']');
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(vec![ip]);
+        }
+        if let Some((_, addresses)) = self.hosts.iter().find(|(pattern, _)| pattern.matches(host)) {
+            debug!("Resolved {} from host overrides: {:?}", host, addresses);
+            return Ok(addresses.clone());
+        }
+        let lookup = self.inner.lookup_ip(host).await.map_err(io::Error::other)?;
+        Ok(lookup.iter().collect())
+    }
+
+    /// Resolves `host` for a connection to `port`, refusing addresses that are our own listeners.
+    pub async fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        let addrs: Vec<SocketAddr> = self.lookup(host).await?.into_iter().map(|ip| SocketAddr::new(ip, port)).collect();
+        if let Some(addr) = addrs.iter().find(|addr| self.is_own_listener(addr)) {
+            let err = LoopDetected { host: host.to_string(), addr: *addr };
+            warn!("{}", err);
+            return Err(io::Error::other(err));
+        }
+        Ok(addrs)
+    }
+
+    fn is_own_listener(&self, addr: &SocketAddr) -> bool {
+        let ip = addr.ip().to_canonical();
+        self.listeners.iter().filter(|l| l.port() == addr.port()).any(|l| {
+            let bound = l.ip();
+            ip == bound
+                || ip.is_unspecified()
+                || (ip.is_loopback() && (bound.is_loopback() || bound.is_unspecified()))
+                || (bound.is_unspecified() && Some(ip) == self.self_ip)
+        })
+    }
+}
+
+/// How long an attempt may run before the next address is tried alongside it (RFC 8305).
+const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Connects to `authority` (`host:port`) through the upstream resolver.
+pub async fn connect(authority: &str) -> io::Result<TcpStream> {
+    let (host, port) = authority
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?)))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid authority '{}'", authority)))?;
+    let mut last_err = io::Error::new(io::ErrorKind::NotFound, format!("no addresses for {}", host));
+    let mut pending = interleave_families(get().resolve(host, port).await?).into_iter();
+    // Yanıt vermeyen bir adres çağıranın zaman aşımının tamamını tüketmesin; denemeler kademeli yarışır
+    let mut attempts = JoinSet::new();
+    loop {
+        if let Some(addr) = pending.next() {
+            attempts.spawn(TcpStream::connect(addr));
+        }
+        let finished = if pending.len() == 0 {
+            match attempts.join_next().await {
+                Some(finished) => finished,
+                None => return Err(last_err),
+            }
+        } else {
+            match tokio::time::timeout(ATTEMPT_DELAY, attempts.join_next()).await {
+                Ok(Some(finished)) => finished,
+                _ => continue,
+            }
+        };
+        // Kazanan döndüğünde JoinSet düşer ve kalan denemeler iptal edilir
+        match finished {
+            Ok(Ok(stream)) => return Ok(stream),
+            Ok(Err(e)) => last_err = e,
+            Err(e) => last_err = io::Error::other(e),
+        }
+    }
+}
+
+/// Alternates address families, keeping the resolver's order within each, so a
+/// broken IPv6 route does not hold up the IPv4 addresses or vice versa.
+fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let Some(prefer_v6) = addrs.first().map(SocketAddr::is_ipv6) else { return addrs };
+    let (primary, secondary): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs.iter().partition(|addr| addr.is_ipv6() == prefer_v6);
+    let (mut primary, mut secondary) = (primary.into_iter(), secondary.into_iter());
+    let mut ordered = Vec::with_capacity(addrs.len());
+    loop {
+        match (primary.next(), secondary.next()) {
+            (None, None) => return ordered,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+/// Adapts the upstream resolver to hyper's `HttpConnector`.
+#[derive(Clone, Default)]
+pub struct HyperResolver;
+
+impl Service<Name> for HyperResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        Box::pin(async move {
+            // Port, bağlayıcı tarafından sonradan atanır
+            let addrs: Vec<SocketAddr> = get().lookup(name.as_str()).await?.into_iter().map(|ip| SocketAddr::new(ip, 0)).collect();
+            Ok(addrs.into_iter())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver(listeners: &[&str], self_ip: Option<&str>) -> UpstreamResolver {
+        UpstreamResolver {
+            inner: TokioAsyncResolver::tokio(ResolverConfig::default(), Default::default()),
+            hosts: Vec::new(),
+            listeners: listeners.iter().map(|l| l.parse().unwrap()).collect(),
+            self_ip: self_ip.map(|ip| ip.parse().unwrap()),
+        }
+    }
+
+    #[tokio::test]
+    async fn wildcard_bind_matches_local_addresses() {
+        let resolver = resolver(&["0.0.0.0:3128", "[::]:1080"], Some("192.168.1.20"));
+        let own = |addr: &str| resolver.is_own_listener(&addr.parse().unwrap());
+        assert!(own("127.0.0.1:3128"));
+        assert!(own("192.168.1.20:3128"));
+        assert!(own("[::ffff:192.168.1.20]:3128"));
+        assert!(own("[::1]:1080"));
+        assert!(own("0.0.0.0:1080"));
+        assert!(!own("192.168.1.21:3128"));
+        assert!(!own("127.0.0.1:8080"));
+    }
+
+    #[tokio::test]
+    async fn specific_bind_matches_only_itself() {
+        let resolver = resolver(&["10.0.0.5:3128"], None);
+        assert!(resolver.is_own_listener(&"10.0.0.5:3128".parse().unwrap()));
+        assert!(!resolver.is_own_listener(&"127.0.0.1:3128".parse().unwrap()));
+    }
+
+    #[test]
+    fn interleaves_address_families() {
+        let addrs: Vec<SocketAddr> = ["[2001:db8::1]:443", "[2001:db8::2]:443", "[2001:db8::3]:443", "192.0.2.1:443"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect();
+        let ordered = interleave_families(addrs.clone());
+        assert_eq!(ordered, vec![addrs[0], addrs[3], addrs[1], addrs[2]]);
+    }
+}
@@ -0,0 +1,74 @@
+// File: crates/service/src/rewrite.rs
+
+use crate::pages;
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+use sentiric_core::HeaderEdits;
+use std::net::IpAddr;
+use tracing::warn;
+
+/// Values available to `{{name}}` placeholders in `rewrite-headers` header values.
+pub struct TemplateVars<'a> {
+    pub client_ip: Option<IpAddr>,
+    pub user: Option<&'a str>,
+    pub host: &'a str,
+    pub method: &'a str,
+    pub url: &'a str,
+}
+
+impl TemplateVars<'_> {
+    fn expand(&self, template: &str) -> String {
+        if !template.contains("{{") {
+            return template.to_string();
+        }
+        let client_ip = self.client_ip.map(|ip| ip.to_canonical().to_string()).unwrap_or_default();
+        let values = [
+            ("client_ip", client_ip.as_str()),
+            ("user", self.user.unwrap_or_default()),
+            ("host", self.host),
+            ("method", self.method),
+            ("url", self.url),
+        ];
+        pages::fill_placeholders(template, &values)
+    }
+}
+
+/// Applies `edits` to `headers`; invalid names or values are logged and skipped.
+pub fn apply(edits: &HeaderEdits, headers: &mut HeaderMap, vars: &TemplateVars) {
+    for name in &edits.remove {
+        if let Some(name) = header_name(name) {
+            headers.remove(name);
+        }
+    }
+    for (name, template) in &edits.set {
+        if let Some((name, value)) = header(name, template, vars) {
+            headers.insert(name, value);
+        }
+    }
+    for (name, template) in &edits.add {
+        if let Some((name, value)) = header(name, template, vars) {
+            headers.append(name, value);
+        }
+    }
+}
+
+fn header(name: &str, template: &str, vars: &TemplateVars) -> Option<(HeaderName, HeaderValue)> {
+    let name = header_name(name)?;
+    let value = vars.expand(template);
+    match HeaderValue::from_str(&value) {
+        Ok(value) => Some((name, value)),
+        Err(_) => {
+            warn!("Skipping header rewrite: invalid value {:?} for {}", value, name);
+            None
+        }
+    }
+}
+
+fn header_name(name: &str) -> Option<HeaderName> {
+    match HeaderName::from_bytes(name.as_bytes()) {
+        Ok(name) => Some(name),
+        Err(_) => {
+            warn!("Skipping header rewrite: invalid header name {:?}", name);
+            None
+        }
+    }
+}
@@ -11,27 +11,27 @@ impl RuleEngine {
         Self { rules }
     }
 
-    pub fn match_action(&self, uri: &str) -> Action {
-        let parsed_uri = match url::Url::parse(uri) {
-            Ok(u) => u,
-            Err(_) => return Action::Allow, // URI parse edilemezse, varsayılan olarak izin ver
-        };
-        
-        let domain = parsed_uri.domain().unwrap_or("");
-
-        for rule in &self.rules {
-            let matched = match &rule.condition {
-                RuleCondition::Domain(d) => d == domain,
-                RuleCondition::UrlPattern(p) => WildMatch::new(p).matches(uri),
-            };
+    pub fn match_action(&self, uri: &str, user: Option<&str>) -> Action {
+        // Hiçbir kural eşleşmezse varsayılan davranış
+        self.match_rule(uri, user).map(|rule| rule.action.clone()).unwrap_or(Action::Allow)
+    }
 
-            if matched {
-                debug!("Request to '{}' matched rule '{}'. Action: {:?}", uri, rule.name, rule.action);
-                return rule.action.clone();
-            }
-        }
+    /// Returns the first rule whose condition matches `uri` and that applies to `user`.
+    pub fn match_rule(&self, uri: &str, user: Option<&str>) -> Option<&Rule> {
+        // URI parse edilemezse hiçbir kural eşleşmez, varsayılan olarak izin verilir
+        let parsed_uri = url::Url::parse(uri).ok()?;
+        let domain = parsed_uri.domain().unwrap_or("");
 
-        // Hiçbir kural eşleşmezse varsayılan davranış
-        Action::Allow
+        let rule = self.rules.iter().filter(|rule| applies_to(rule, user)).find(|rule| match &rule.condition {
+            RuleCondition::Domain(d) => d == domain,
+            RuleCondition::UrlPattern(p) => WildMatch::new(p).matches(uri),
+        })?;
+        debug!("Request to '{}' matched rule '{}'. Action: {:?}", uri, rule.name, rule.action);
+        Some(rule)
     }
-}
\ No newline at end of file
+}
+
+// Kullanıcı listesi boş olan kurallar herkese uygulanır
+fn applies_to(rule: &Rule, user: Option<&str>) -> bool {
+    rule.users.is_empty() || user.is_some_and(|user| rule.users.iter().any(|u| u == user))
+}
@@ -0,0 +1,83 @@
+// File: crates/service/src/shutdown.rs
+
+use hyper::server::conn::Http;
+use hyper::service::Service;
+use hyper::{Body, Request, Response};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{watch, Notify};
+
+lazy_static::lazy_static! {
+    static ref SIGNAL: watch::Sender<bool> = watch::channel(false).0;
+    static ref DRAINED: Notify = Notify::new();
+}
+
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// Asks every listener and connection to wind down.
+pub fn trigger() {
+    SIGNAL.send_replace(true);
+}
+
+/// Resolves once shutdown has been triggered.
+pub async fn requested() {
+    let mut rx = SIGNAL.subscribe();
+    let _ = rx.wait_for(|requested| *requested).await;
+}
+
+/// Marks work that shutdown should wait for; released when dropped.
+pub struct Guard(());
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if ACTIVE.fetch_sub(1, Ordering::AcqRel) == 1 {
+            DRAINED.notify_waiters();
+        }
+    }
+}
+
+pub fn track() -> Guard {
+    ACTIVE.fetch_add(1, Ordering::AcqRel);
+    Guard(())
+}
+
+pub fn active() -> usize {
+    ACTIVE.load(Ordering::Acquire)
+}
+
+/// Waits until all tracked work has finished. Returns false if `timeout` elapsed first.
+pub async fn drain(timeout: Duration) -> bool {
+    tokio::time::timeout(timeout, async {
+        loop {
+            // Bildirimi kaçırmamak için sayacı kontrol etmeden önce kayıt ol
+            let notified = DRAINED.notified();
+            if active() == 0 {
+                return;
+            }
+            notified.await;
+        }
+    })
+    .await
+    .is_ok()
+}
+
+/// Serves a hyper connection, letting the in-flight request finish and then
+/// closing it when shutdown is triggered.
+pub async fn serve_connection<IO, S>(http: &Http, io: IO, service: S) -> Result<(), hyper::Error>
+where
+    IO: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Service<Request<Body>, Response = Response<Body>, Error = hyper::Error> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    let _guard = track();
+    let conn = http.serve_connection(io, service).with_upgrades();
+    tokio::pin!(conn);
+    tokio::select! {
+        res = conn.as_mut() => res,
+        _ = requested() => {
+            conn.as_mut().graceful_shutdown();
+            conn.await
+        }
+    }
+}
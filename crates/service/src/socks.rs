@@ -0,0 +1,235 @@
+// File: crates/service/src/socks.rs
+
+use crate::acl::AccessControl;
+use crate::auth;
+use crate::cache::CacheManager;
+use crate::certs::CertificateAuthority;
+use crate::passthrough::PassthroughRegistry;
+use crate::proxy::{self, ClientInfo};
+use crate::proxy_protocol::ProxyProtocolAcceptor;
+use crate::{shutdown, transparent};
+use anyhow::{bail, Context, Result};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use sentiric_core::Socks5;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn, Instrument};
+
+const SOCKS_VERSION: u8 = 0x05;
+const METHOD_NO_AUTH: u8 = 0x00;
+const METHOD_USER_PASS: u8 = 0x02;
+const METHOD_NONE_ACCEPTABLE: u8 = 0xFF;
+const CMD_CONNECT: u8 = 0x01;
+const REPLY_SUCCEEDED: u8 = 0x00;
+const REPLY_COMMAND_NOT_SUPPORTED: u8 = 0x07;
+const REPLY_ADDRESS_NOT_SUPPORTED: u8 = 0x08;
+
+/// How long we wait for the client to speak first before treating the
+/// connection as an opaque (e.g. server-first) protocol and relaying it.
+const SNIFF_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Destination requested by a SOCKS5 `CONNECT`.
+struct Target {
+    host: String,
+    port: u16,
+    /// False when the client sent a literal IP (`socks5://` instead of `socks5h://`).
+    is_domain: bool,
+}
+
+pub async fn run_server(
+    listener: TcpListener,
+    ca: Arc<CertificateAuthority>,
+    cache: Arc<CacheManager>,
+    passthrough: Arc<PassthroughRegistry>,
+    access: Arc<AccessControl>,
+    proxy_protocol: Arc<ProxyProtocolAcceptor>,
+) -> Result<()> {
+    info!("🚀 SOCKS5 proxy listening on socks5://{}", listener.local_addr()?);
+
+    loop {
+        let (mut stream, peer_addr) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = shutdown::requested() => return Ok(()),
+        };
+        let ca = ca.clone();
+        let cache = cache.clone();
+        let passthrough = passthrough.clone();
+        let access = access.clone();
+        let proxy_protocol = proxy_protocol.clone();
+
+        tokio::spawn(async move {
+            let _guard = shutdown::track();
+            let Some(client_addr) = proxy_protocol.client_addr(&mut stream, peer_addr).await else {
+                return;
+            };
+            if !access.check(client_addr.ip()) {
+                return;
+            }
+            async move {
+                if let Err(e) = handle_client(stream, client_addr, ca, cache, passthrough).await {
+                    debug!(cause = ?e, "SOCKS5 connection error");
+                }
+            }
+            .instrument(tracing::info_span!("socks5", %client_addr))
+            .await
+        });
+    }
+}
+
+async fn handle_client(
+    mut stream: TcpStream,
+    client_addr: SocketAddr,
+    ca: Arc<CertificateAuthority>,
+    cache: Arc<CacheManager>,
+    passthrough: Arc<PassthroughRegistry>,
+) -> Result<()> {
+    let settings = &crate::config::get().proxy.socks5;
+    let cache_headers = &settings.cache_headers;
+    let client_info = ClientInfo {
+        user: negotiate_auth(&mut stream, settings).await?,
+        ip: Some(client_addr.ip()),
+    };
+    let target = read_connect_request(&mut stream).await?;
+    stream
+        .write_all(&[SOCKS_VERSION, REPLY_SUCCEEDED, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+        .await?;
+
+    // Tünelin içinde ne konuşulduğuna bakıp HTTP/S boru hattına ya da ham aktarıma yönlendir.
+    let mut first = [0u8; 1];
+    let sniffed = tokio::time::timeout(SNIFF_TIMEOUT, stream.peek(&mut first)).await;
+    match sniffed {
+        Ok(Ok(0)) => Ok(()),
+        Ok(Ok(_)) if first[0] == 0x16 => {
+            let hostname = if target.is_domain {
+                target.host.clone()
+            } else {
+                // Çıplak IP ile gelen istemciler için sertifikayı SNI'dan üret.
+                // Sessiz kalan istemci bağlantıyı sonsuza dek tutmasın.
+                let peek = tokio::time::timeout(transparent::CLIENT_HELLO_TIMEOUT, transparent::peek_sni(&stream));
+                match peek.await {
+                    Ok(Ok(sni)) => sni,
+                    _ => target.host.clone(),
+                }
+            };
+            let host = format!("{}:{}", hostname, target.port);
+            if let Some(reason) = proxy::passthrough_reason(&hostname, &client_info, &passthrough) {
+                proxy::tunnel(stream, host, "TLS", Some(reason), client_info).await
+            } else {
+                proxy::serve_https(stream, host, ca, cache, passthrough, client_info, cache_headers).await
+            }
+        }
+        Ok(Ok(_)) if first[0].is_ascii_uppercase() => {
+            let service = service_fn(move |req| proxy::serve_http(req, cache.clone(), false, client_info.clone(), cache_headers));
+            shutdown::serve_connection(Http::new().http1_only(true), stream, service)
+                .await
+                .context("Error serving HTTP over SOCKS5")
+        }
+        Ok(Err(e)) => Err(e.into()),
+        // Sunucunun önce konuştuğu (SSH gibi) veya tanınmayan protokoller.
+        Ok(Ok(_)) | Err(_) => {
+            let host = format!("{}:{}", target.host, target.port);
+            proxy::tunnel(stream, host, "TCP", None, client_info).await
+        }
+    }
+}
+
+/// Runs method negotiation and, if required, RFC 1929 login. Returns the authenticated user.
+async fn negotiate_auth(stream: &mut TcpStream, settings: &Socks5) -> Result<Option<String>> {
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header).await?;
+    if header[0] != SOCKS_VERSION {
+        bail!("unsupported SOCKS version {}", header[0]);
+    }
+    let mut methods = vec![0u8; header[1] as usize];
+    stream.read_exact(&mut methods).await?;
+
+    // Sabit kimlik bilgileri tanımlı değilse proxy kullanıcı deposuna düş.
+    let credentials = settings.username.as_deref().zip(settings.password.as_deref());
+    let wanted = if credentials.is_some() || auth::is_enabled() { METHOD_USER_PASS } else { METHOD_NO_AUTH };
+    if !methods.contains(&wanted) {
+        stream.write_all(&[SOCKS_VERSION, METHOD_NONE_ACCEPTABLE]).await?;
+        bail!("client offered no acceptable authentication method");
+    }
+    stream.write_all(&[SOCKS_VERSION, wanted]).await?;
+
+    if wanted == METHOD_NO_AUTH {
+        return Ok(None);
+    }
+
+    // RFC 1929: VER | ULEN | UNAME | PLEN | PASSWD
+    let mut version_and_len = [0u8; 2];
+    stream.read_exact(&mut version_and_len).await?;
+    let mut uname = vec![0u8; version_and_len[1] as usize];
+    stream.read_exact(&mut uname).await?;
+    let plen = stream.read_u8().await? as usize;
+    let mut passwd = vec![0u8; plen];
+    stream.read_exact(&mut passwd).await?;
+
+    let uname = String::from_utf8_lossy(&uname).into_owned();
+    let passwd = String::from_utf8_lossy(&passwd);
+    let valid = match credentials {
+        // İki alan da her zaman karşılaştırılır; yanıt süresi hangisinin yanlış olduğunu ele vermez
+        Some((username, password)) => {
+            auth::constant_time_eq(uname.as_bytes(), username.as_bytes())
+                & auth::constant_time_eq(passwd.as_bytes(), password.as_bytes())
+        }
+        None => auth::verify(&uname, &passwd),
+    };
+    if valid {
+        stream.write_all(&[0x01, 0x00]).await?;
+        Ok(Some(uname))
+    } else {
+        stream.write_all(&[0x01, 0x01]).await?;
+        warn!("SOCKS5 authentication failed for user '{}'", uname);
+        bail!("invalid SOCKS5 credentials")
+    }
+}
+
+async fn read_connect_request(stream: &mut TcpStream) -> Result<Target> {
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).await?;
+    let [version, command, _, address_type] = header;
+    if version != SOCKS_VERSION {
+        bail!("unsupported SOCKS version {}", version);
+    }
+
+    let (host, is_domain) = match address_type {
+        0x01 => {
+            let mut octets = [0u8; 4];
+            stream.read_exact(&mut octets).await?;
+            (Ipv4Addr::from(octets).to_string(), false)
+        }
+        0x03 => {
+            let len = stream.read_u8().await? as usize;
+            let mut name = vec![0u8; len];
+            stream.read_exact(&mut name).await?;
+            (String::from_utf8(name).context("invalid domain name")?, true)
+        }
+        0x04 => {
+            let mut octets = [0u8; 16];
+            stream.read_exact(&mut octets).await?;
+            (format!("[{}]", Ipv6Addr::from(octets)), false)
+        }
+        other => {
+            reply_error(stream, REPLY_ADDRESS_NOT_SUPPORTED).await;
+            bail!("unsupported address type {}", other);
+        }
+    };
+    let port = stream.read_u16().await?;
+
+    if command != CMD_CONNECT {
+        reply_error(stream, REPLY_COMMAND_NOT_SUPPORTED).await;
+        bail!("unsupported SOCKS5 command {}", command);
+    }
+    Ok(Target { host, port, is_domain })
+}
+
+async fn reply_error(stream: &mut TcpStream, code: u8) {
+    let _ = stream
+        .write_all(&[SOCKS_VERSION, code, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+        .await;
+}
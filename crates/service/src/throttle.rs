@@ -0,0 +1,164 @@
+// File: crates/service/src/throttle.rs
+
+use futures_util::StreamExt;
+use hyper::Body;
+use lazy_static::lazy_static;
+use sentiric_core::{BucketStatus, Throttle, ThrottleStatus};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    static ref LIMITER: Limiter = Limiter::new(crate::config::get().throttle.clone());
+}
+
+/// Token bucket for upstream download limits, holding at most one second's worth of tokens.
+struct TokenBucket {
+    rate: u64,
+    // (mevcut jeton, son dolum zamanı); jeton eksiye düşebilir, borç bekleyerek ödenir
+    state: Mutex<(f64, Instant)>,
+    transferred: AtomicU64,
+    delayed_ms: AtomicU64,
+}
+
+impl TokenBucket {
+    fn new(rate: u64) -> Self {
+        Self {
+            rate,
+            state: Mutex::new((rate as f64, Instant::now())),
+            transferred: AtomicU64::new(0),
+            delayed_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// Takes `bytes` tokens and returns how long the caller must wait to stay under the rate.
+    fn reserve(&self, bytes: u64) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let (tokens, last) = &mut *state;
+        let now = Instant::now();
+        let capacity = self.rate as f64;
+        *tokens = (*tokens + now.duration_since(*last).as_secs_f64() * capacity).min(capacity);
+        *last = now;
+        *tokens -= bytes as f64;
+        self.transferred.fetch_add(bytes, Ordering::Relaxed);
+        if *tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-*tokens / capacity)
+        }
+    }
+
+    fn status(&self, key: &str) -> BucketStatus {
+        BucketStatus {
+            key: key.to_string(),
+            bytes_per_sec: self.rate,
+            bytes_transferred: self.transferred.load(Ordering::Relaxed),
+            delayed_ms: self.delayed_ms.load(Ordering::Relaxed),
+        }
+    }
+}
+
+type Buckets<K> = Mutex<HashMap<K, Arc<TokenBucket>>>;
+
+struct Limiter {
+    settings: Throttle,
+    global: Option<Arc<TokenBucket>>,
+    clients: Buckets<IpAddr>,
+    domains: Buckets<String>,
+    rules: Buckets<String>,
+}
+
+impl Limiter {
+    fn new(settings: Throttle) -> Self {
+        Self {
+            global: settings.global_bytes_per_sec.map(|rate| Arc::new(TokenBucket::new(rate))),
+            settings,
+            clients: Mutex::default(),
+            domains: Mutex::default(),
+            rules: Mutex::default(),
+        }
+    }
+}
+
+fn bucket<K: std::hash::Hash + Eq>(buckets: &Buckets<K>, key: K, rate: u64) -> Arc<TokenBucket> {
+    let mut buckets = buckets.lock().unwrap();
+    let bucket = buckets.entry(key).or_insert_with(|| Arc::new(TokenBucket::new(rate)));
+    // Kural hızı yapılandırmada değiştiyse kovayı yenile
+    if bucket.rate != rate {
+        *bucket = Arc::new(TokenBucket::new(rate));
+    }
+    bucket.clone()
+}
+
+/// Which limits apply to one response.
+#[derive(Default)]
+pub struct Scope<'a> {
+    pub client: Option<IpAddr>,
+    pub domain: Option<&'a str>,
+    /// Name and rate of a matching `throttle` rule.
+    pub rule: Option<(&'a str, u64)>,
+    pub is_hit: bool,
+}
+
+/// Wraps `body` so it is delivered no faster than every limit in `scope` allows.
+pub fn limit(body: Body, scope: Scope) -> Body {
+    let limiter = &*LIMITER;
+    if scope.is_hit && !limiter.settings.throttle_cache_hits {
+        return body;
+    }
+
+    let mut buckets: Vec<Arc<TokenBucket>> = limiter.global.iter().cloned().collect();
+    if let (Some(ip), Some(rate)) = (scope.client, limiter.settings.per_client_bytes_per_sec) {
+        buckets.push(bucket(&limiter.clients, ip, rate));
+    }
+    // Önbellekten sunulan yanıtlar origin'e yük bindirmez; alan adı ve kural sınırları yalnızca indirmelere uygulanır.
+    if !scope.is_hit {
+        if let (Some(domain), Some(rate)) = (scope.domain, limiter.settings.per_domain_bytes_per_sec) {
+            buckets.push(bucket(&limiter.domains, domain.to_string(), rate));
+        }
+        if let Some((name, rate)) = scope.rule {
+            buckets.push(bucket(&limiter.rules, name.to_string(), rate));
+        }
+    }
+    if buckets.is_empty() {
+        return body;
+    }
+
+    Body::wrap_stream(body.then(move |chunk| {
+        let mut wait = Duration::ZERO;
+        if let Ok(bytes) = &chunk {
+            let waits: Vec<Duration> = buckets.iter().map(|b| b.reserve(bytes.len() as u64)).collect();
+            wait = waits.iter().copied().max().unwrap_or_default();
+            // Bekleme süresini yalnızca darboğaz olan kovaya yaz
+            if let Some(index) = waits.iter().position(|w| !w.is_zero() && *w == wait) {
+                buckets[index].delayed_ms.fetch_add(wait.as_millis() as u64, Ordering::Relaxed);
+            }
+        }
+        async move {
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+            chunk
+        }
+    }))
+}
+
+pub fn status() -> ThrottleStatus {
+    let limiter = &*LIMITER;
+    let list = |buckets: &Buckets<String>| {
+        let mut list: Vec<BucketStatus> = buckets.lock().unwrap().iter().map(|(key, b)| b.status(key)).collect();
+        list.sort_by(|a, b| a.key.cmp(&b.key));
+        list
+    };
+    let mut clients: Vec<BucketStatus> =
+        limiter.clients.lock().unwrap().iter().map(|(ip, b)| b.status(&ip.to_string())).collect();
+    clients.sort_by(|a, b| a.key.cmp(&b.key));
+    ThrottleStatus {
+        global: limiter.global.as_ref().map(|b| b.status("*")),
+        clients,
+        domains: list(&limiter.domains),
+        rules: list(&limiter.rules),
+    }
+}
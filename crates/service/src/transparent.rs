@@ -0,0 +1,293 @@
+// File: crates/service/src/transparent.rs
+
+use crate::acl::{AccessControl, Listener};
+use crate::cache::CacheManager;
+use crate::certs::CertificateAuthority;
+use crate::listen;
+use crate::passthrough::PassthroughRegistry;
+use crate::proxy::{self, ClientInfo};
+use crate::shutdown;
+use anyhow::{bail, Result};
+use futures_util::FutureExt;
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use sentiric_core::{Settings, Transparent};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn, Instrument};
+
+/// Largest TLS record we wait for while looking for the SNI extension.
+const MAX_CLIENT_HELLO: usize = 5 + 16 * 1024;
+pub(crate) const CLIENT_HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Transparent clients cannot send `Proxy-Authorization`, so with `[proxy.auth]`
+/// on they must be restricted by a `[proxy.access]` allow list instead.
+pub fn validate(settings: &Settings) -> Result<()> {
+    if settings.transparent.enabled && settings.proxy.auth.enabled && settings.proxy.access.allow.is_empty() {
+        bail!("[transparent] listeners cannot authenticate clients; set [proxy.access] allow when [proxy.auth] is enabled");
+    }
+    Ok(())
+}
+
+/// Runs the plain HTTP and HTTPS listeners used by DNS-redirected clients.
+///
+/// HTTP requests are routed by their `Host` header; TLS connections by the SNI
+/// in the ClientHello, which is peeked so the connection can still be spliced
+/// untouched for passthrough hosts.
+pub async fn run_servers(
+    settings: &Transparent,
+    ca: Arc<CertificateAuthority>,
+    cache: Arc<CacheManager>,
+    passthrough: Arc<PassthroughRegistry>,
+) -> Result<()> {
+    // Şeffaf dinleyiciler de proxy erişim listesine tabidir.
+    let access = Arc::new(AccessControl::new(Listener::Proxy, &crate::config::get().proxy.access)?);
+    let mut servers = Vec::new();
+    for addr in listen::socket_addrs(&settings.bind_address, settings.http_port)? {
+        servers.push(run_http_server(listen::bind_tcp(addr)?, cache.clone(), access.clone()).boxed());
+    }
+    for addr in listen::socket_addrs(&settings.bind_address, settings.https_port)? {
+        let listener = listen::bind_tcp(addr)?;
+        servers.push(run_https_server(listener, ca.clone(), cache.clone(), passthrough.clone(), access.clone()).boxed());
+    }
+    futures_util::future::try_join_all(servers).await?;
+    Ok(())
+}
+
+async fn run_http_server(listener: TcpListener, cache: Arc<CacheManager>, access: Arc<AccessControl>) -> Result<()> {
+    info!("🚀 Transparent HTTP listener on http://{}", listener.local_addr()?);
+    let cache_headers = &crate::config::get().transparent.cache_headers;
+
+    loop {
+        let (stream, client_addr) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = shutdown::requested() => return Ok(()),
+        };
+        if !access.check(client_addr.ip()) {
+            continue;
+        }
+        let cache = cache.clone();
+        let client_info = ClientInfo { ip: Some(client_addr.ip()), ..Default::default() };
+        let service = service_fn(move |req| proxy::serve_http(req, cache.clone(), false, client_info.clone(), cache_headers));
+
+        tokio::spawn(
+            async move {
+                if let Err(err) = shutdown::serve_connection(Http::new().http1_only(true), stream, service).await {
+                    debug!(cause = ?err, "Transparent HTTP connection error");
+                }
+            }
+            .instrument(tracing::info_span!("transparent", %client_addr)),
+        );
+    }
+}
+
+async fn run_https_server(
+    listener: TcpListener,
+    ca: Arc<CertificateAuthority>,
+    cache: Arc<CacheManager>,
+    passthrough: Arc<PassthroughRegistry>,
+    access: Arc<AccessControl>,
+) -> Result<()> {
+    info!("🚀 Transparent HTTPS listener on https://{}", listener.local_addr()?);
+    let cache_headers = &crate::config::get().transparent.cache_headers;
+
+    loop {
+        let (stream, client_addr) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = shutdown::requested() => return Ok(()),
+        };
+        if !access.check(client_addr.ip()) {
+            continue;
+        }
+        let ca = ca.clone();
+        let cache = cache.clone();
+        let passthrough = passthrough.clone();
+
+        tokio::spawn(
+            async move {
+                let _guard = shutdown::track();
+                let sni = match tokio::time::timeout(CLIENT_HELLO_TIMEOUT, peek_sni(&stream)).await {
+                    Ok(Ok(sni)) => sni,
+                    Ok(Err(e)) => {
+                        warn!("Dropping transparent TLS connection: {}", e);
+                        return;
+                    }
+                    Err(_) => {
+                        debug!("Timed out waiting for ClientHello");
+                        return;
+                    }
+                };
+
+                // CONNECT üzerinden gelen isteklerle aynı önbellek anahtarını kullanmak için portu ekle.
+                let host = format!("{}:443", sni);
+                let client_info = ClientInfo { ip: Some(client_addr.ip()), ..Default::default() };
+                if let Some(reason) = proxy::passthrough_reason(&sni, &client_info, &passthrough) {
+                    if let Err(e) = proxy::tunnel(stream, host, "TLS", Some(reason), client_info).await {
+                        warn!(cause = ?e, "TLS passthrough error");
+                    }
+                } else if let Err(e) = proxy::serve_https(stream, host, ca, cache, passthrough, client_info, cache_headers).await {
+                    if !e.to_string().contains("TLS handshake failed") {
+                        warn!(cause = ?e, "Transparent HTTPS error");
+                    }
+                }
+            }
+            .instrument(tracing::info_span!("transparent", %client_addr)),
+        );
+    }
+}
+
+/// Waits until the first TLS record is buffered and returns its SNI host name
+/// without consuming any bytes from the socket.
+pub(crate) async fn peek_sni(stream: &TcpStream) -> Result<String> {
+    let mut buf = vec![0u8; MAX_CLIENT_HELLO];
+    loop {
+        let n = stream.peek(&mut buf).await?;
+        if n == 0 {
+            bail!("connection closed before ClientHello");
+        }
+        if n >= 5 {
+            if buf[0] != 0x16 {
+                bail!("not a TLS handshake");
+            }
+            let record_len = 5 + u16::from_be_bytes([buf[3], buf[4]]) as usize;
+            if n >= record_len || n == buf.len() {
+                let sni = parse_sni(&buf[5..n.min(record_len)]).ok_or_else(|| anyhow::anyhow!("ClientHello carries no SNI"))?;
+                // Ad, yönlendirme için URI yetki alanı olarak kullanılır
+                if sni.parse::<http::uri::Authority>().is_err() {
+                    bail!("ClientHello SNI {:?} is not a valid host name", sni);
+                }
+                return Ok(sni);
+            }
+        }
+        // Kaydın geri kalanı henüz gelmedi.
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// Extracts the `server_name` extension from a ClientHello handshake message.
+fn parse_sni(handshake: &[u8]) -> Option<String> {
+    struct Reader<'a>(&'a [u8]);
+    impl<'a> Reader<'a> {
+        fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+            if self.0.len() < n {
+                return None;
+            }
+            let (head, tail) = self.0.split_at(n);
+            self.0 = tail;
+            Some(head)
+        }
+        fn u8(&mut self) -> Option<usize> {
+            self.take(1).map(|b| b[0] as usize)
+        }
+        fn u16(&mut self) -> Option<usize> {
+            self.take(2).map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+        }
+    }
+
+    let mut r = Reader(handshake);
+    if r.u8()? != 0x01 {
+        return None; // ClientHello değil
+    }
+    r.take(3)?; // handshake uzunluğu
+    r.take(2 + 32)?; // sürüm + random
+    let session_id_len = r.u8()?;
+    r.take(session_id_len)?;
+    let cipher_suites_len = r.u16()?;
+    r.take(cipher_suites_len)?;
+    let compression_len = r.u8()?;
+    r.take(compression_len)?;
+    let extensions_len = r.u16()?;
+    let mut extensions = Reader(r.take(extensions_len)?);
+
+    while !extensions.0.is_empty() {
+        let ext_type = extensions.u16()?;
+        let ext_len = extensions.u16()?;
+        let mut ext = Reader(extensions.take(ext_len)?);
+        if ext_type != 0x0000 {
+            continue;
+        }
+        let list_len = ext.u16()?;
+        let mut names = Reader(ext.take(list_len)?);
+        while !names.0.is_empty() {
+            let name_type = names.u8()?;
+            let name_len = names.u16()?;
+            let name = names.take(name_len)?;
+            if name_type == 0 {
+                return std::str::from_utf8(name).ok().map(str::to_ascii_lowercase);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extension(ext_type: u16, data: &[u8]) -> Vec<u8> {
+        let mut out = ext_type.to_be_bytes().to_vec();
+        out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        out.extend_from_slice(data);
+        out
+    }
+
+    fn server_name(host: &str) -> Vec<u8> {
+        let mut entry = vec![0u8];
+        entry.extend_from_slice(&(host.len() as u16).to_be_bytes());
+        entry.extend_from_slice(host.as_bytes());
+        let mut list = (entry.len() as u16).to_be_bytes().to_vec();
+        list.extend(entry);
+        extension(0x0000, &list)
+    }
+
+    fn client_hello(extensions: &[Vec<u8>]) -> Vec<u8> {
+        let mut body = vec![0x03, 0x03];
+        body.extend_from_slice(&[0xAB; 32]);
+        body.push(32);
+        body.extend_from_slice(&[0xCD; 32]);
+        body.extend_from_slice(&[0x00, 0x04, 0x13, 0x01, 0x13, 0x02]);
+        body.extend_from_slice(&[0x01, 0x00]);
+        let extensions: Vec<u8> = extensions.concat();
+        body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+        body.extend(extensions);
+
+        let mut hello = vec![0x01];
+        hello.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+        hello.extend(body);
+        hello
+    }
+
+    #[test]
+    fn finds_sni_among_other_extensions() {
+        let hello = client_hello(&[
+            extension(0x000a, &[0x00, 0x02, 0x00, 0x1d]),
+            extension(0x0010, &[0x00, 0x03, 0x02, b'h', b'2']),
+            server_name("Example.COM"),
+            extension(0x002b, &[0x02, 0x03, 0x04]),
+        ]);
+        assert_eq!(parse_sni(&hello).as_deref(), Some("example.com"));
+    }
+
+    #[test]
+    fn returns_none_without_sni() {
+        let hello = client_hello(&[extension(0x000a, &[0x00, 0x02, 0x00, 0x1d])]);
+        assert_eq!(parse_sni(&hello), None);
+        assert_eq!(parse_sni(&client_hello(&[])), None);
+    }
+
+    #[test]
+    fn returns_none_for_truncated_hello() {
+        let hello = client_hello(&[server_name("example.com")]);
+        for len in [0, 1, 4, 40, hello.len() - 1] {
+            assert_eq!(parse_sni(&hello[..len]), None, "prefix of {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn rejects_other_handshake_messages() {
+        let mut hello = client_hello(&[server_name("example.com")]);
+        hello[0] = 0x02;
+        assert_eq!(parse_sni(&hello), None);
+    }
+}
@@ -0,0 +1,543 @@
+// File: crates/service/src/upstream.rs
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use crate::resolver::{self, HyperResolver};
+use hyper::client::connect::{Connected, Connection};
+use hyper::client::HttpConnector;
+use hyper::service::Service;
+use hyper::header::HeaderValue;
+use hyper::Uri;
+use hyper_rustls::MaybeHttpsStream;
+use lazy_static::lazy_static;
+use sentiric_core::Upstream;
+use std::future::Future;
+use std::io::IoSlice;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{self, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::TcpStream;
+use tracing::{debug, warn};
+use url::Url;
+use wildmatch::WildMatch;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Longest CONNECT response header we accept from a parent proxy.
+const MAX_CONNECT_RESPONSE: usize = 8 * 1024;
+
+#[derive(Debug, Clone)]
+enum ProxyKind {
+    HttpConnect,
+    Socks5,
+}
+
+#[derive(Debug, Clone)]
+struct ParentProxy {
+    kind: ProxyKind,
+    host: String,
+    port: u16,
+    credentials: Option<(String, String)>,
+}
+
+impl ParentProxy {
+    fn parse(raw: &str) -> Result<Self> {
+        let url = Url::parse(raw).with_context(|| format!("Invalid upstream proxy URL '{}'", raw))?;
+        let (kind, default_port) = match url.scheme() {
+            "http" => (ProxyKind::HttpConnect, 80),
+            "socks5" | "socks5h" => (ProxyKind::Socks5, 1080),
+            other => bail!("Unsupported upstream proxy scheme '{}'", other),
+        };
+        let host = url.host_str().context("Upstream proxy URL has no host")?.to_string();
+        let decode = |s: &str| percent_encoding::percent_decode_str(s).decode_utf8_lossy().into_owned();
+        let credentials = (!url.username().is_empty())
+            .then(|| (decode(url.username()), decode(url.password().unwrap_or(""))));
+        Ok(Self {
+            kind,
+            host,
+            port: url.port().unwrap_or(default_port),
+            credentials,
+        })
+    }
+
+    fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+/// Where connections to a given origin go.
+#[derive(Debug, Clone)]
+enum Route {
+    Direct,
+    Via(Arc<ParentProxy>),
+}
+
+/// Proxy selection resolved once from `[upstream]` and the environment.
+struct Routes {
+    https: Option<Arc<ParentProxy>>,
+    http: Option<Arc<ParentProxy>>,
+    no_proxy: Vec<String>,
+    overrides: Vec<(String, Route)>,
+}
+
+impl Routes {
+    fn from_settings(settings: &Upstream) -> Result<Self> {
+        let parse = |raw: &str| ParentProxy::parse(raw).map(Arc::new);
+        let overrides = settings
+            .overrides
+            .iter()
+            .map(|o| {
+                let route = if o.proxy.eq_ignore_ascii_case("direct") {
+                    Route::Direct
+                } else {
+                    Route::Via(parse(&o.proxy)?)
+                };
+                Ok((o.domain.clone(), route))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut no_proxy = settings.no_proxy.clone();
+
+        if let Some(raw) = &settings.proxy {
+            let proxy = parse(raw)?;
+            return Ok(Self { https: Some(proxy.clone()), http: Some(proxy), no_proxy, overrides });
+        }
+        if !settings.use_env {
+            return Ok(Self { https: None, http: None, no_proxy, overrides });
+        }
+
+        let from_env = |names: &[&str]| -> Result<Option<Arc<ParentProxy>>> {
+            let Some(raw) = names.iter().find_map(|n| std::env::var(n).ok().filter(|v| !v.is_empty())) else {
+                return Ok(None);
+            };
+            let proxy = parse(&raw)?;
+            if points_at_ourselves(&proxy) {
+                warn!("Ignoring upstream proxy {} from the environment: it points at this proxy", raw);
+                return Ok(None);
+            }
+            Ok(Some(proxy))
+        };
+        let all = from_env(&["all_proxy", "ALL_PROXY"])?;
+        let https = from_env(&["https_proxy", "HTTPS_PROXY"])?.or_else(|| all.clone());
+        let http = from_env(&["http_proxy", "HTTP_PROXY"])?.or(all);
+        if let Some(raw) = ["no_proxy", "NO_PROXY"].iter().find_map(|n| std::env::var(n).ok()) {
+            no_proxy.extend(raw.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()));
+        }
+        Ok(Self { https, http, no_proxy, overrides })
+    }
+
+    fn route(&self, host: &str, is_https: bool) -> Route {
+        if let Some((_, route)) = self.overrides.iter().find(|(d, _)| WildMatch::new(d).matches(host)) {
+            return route.clone();
+        }
+        if self.no_proxy.iter().any(|pattern| no_proxy_matches(pattern, host)) {
+            return Route::Direct;
+        }
+        let proxy = if is_https { &self.https } else { &self.http };
+        proxy.clone().map_or(Route::Direct, Route::Via)
+    }
+}
+
+fn no_proxy_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim();
+    if pattern == "*" {
+        return true;
+    }
+    if let Some(suffix) = pattern.strip_prefix('.') {
+        return host == suffix || host.ends_with(pattern);
+    }
+    if pattern.contains(['*', '?']) {
+        return WildMatch::new(pattern).matches(host);
+    }
+    // curl ile aynı: çıplak "example.com" alt alan adlarını da kapsar
+    host == pattern || host.strip_suffix(pattern).is_some_and(|rest| rest.ends_with('.'))
+}
+
+/// Prevents `HTTPS_PROXY=http://127.0.0.1:3128` style loops when the service is
+/// started from a shell that is itself configured to use this proxy.
+fn points_at_ourselves(proxy: &ParentProxy) -> bool {
+    let settings = crate::config::get();
+    let is_local = matches!(proxy.host.as_str(), "localhost" | "127.0.0.1" | "::1" | "[::1]" | "0.0.0.0")
+        || settings.proxy.bind_address.iter().any(|address| address == proxy.host);
+    is_local && (proxy.port == settings.proxy.port || proxy.port == settings.proxy.socks5.port)
+}
+
+lazy_static! {
+    static ref ROUTES: Routes = match Routes::from_settings(&crate::config::get().upstream) {
+        Ok(routes) => routes,
+        Err(e) => {
+            warn!("Invalid [upstream] configuration, connecting directly: {:#}", e);
+            Routes { https: None, http: None, no_proxy: Vec::new(), overrides: Vec::new() }
+        }
+    };
+}
+
+/// Checks the `[upstream]` section at startup so mistakes surface before the first request.
+pub fn validate(settings: &Upstream) -> Result<()> {
+    Routes::from_settings(settings).map(|_| ())
+}
+
+/// TCP connector for the shared HTTP clients; TLS is layered on top by `hyper-rustls`.
+/// Depending on `[upstream]`, the TCP stream is opened directly or through a parent
+/// proxy tunnel (HTTP `CONNECT` or SOCKS5).
+#[derive(Clone)]
+pub struct UpstreamConnector {
+    direct: HttpConnector<HyperResolver>,
+}
+
+impl UpstreamConnector {
+    pub fn new() -> Self {
+        let settings = &crate::config::get().downloader;
+        let mut direct = HttpConnector::new_with_resolver(HyperResolver);
+        direct.enforce_http(false);
+        direct.set_nodelay(true);
+        direct.set_connect_timeout(Some(Duration::from_secs(settings.connect_timeout_secs)));
+        direct.set_happy_eyeballs_timeout(Some(Duration::from_millis(settings.happy_eyeballs_timeout_ms)));
+        Self { direct }
+    }
+}
+
+impl Default for UpstreamConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Service<Uri> for UpstreamConnector {
+    type Response = UpstreamStream;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<UpstreamStream, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.direct.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let mut direct = self.direct.clone();
+        Box::pin(async move {
+            let host = dst.host().ok_or("URI has no host")?.trim_matches(|c| c == '[' || c == ']').to_string();
+            let is_https = dst.scheme_str() == Some("https");
+            let port = dst.port_u16().unwrap_or(if is_https { 443 } else { 80 });
+
+            match ROUTES.route(&host, is_https) {
+                Route::Via(proxy) if matches!(proxy.kind, ProxyKind::HttpConnect) && !is_https => {
+                    // Düz HTTP istekleri tünellenmez; üst proxy'ye mutlak URI ile gönderilir
+                    debug!("Sending http://{}:{} requests to parent proxy {}", host, port, proxy.address());
+                    let started = Instant::now();
+                    let stream = connect_to_proxy(&proxy).await?;
+                    Ok(UpstreamStream { stream, dns: None, connect: started.elapsed(), proxied: true })
+                }
+                Route::Direct => {
+                    // Kendi dinleyicilerimize geri dönen hedefleri bağlanmadan reddet
+                    let started = Instant::now();
+                    resolver::get().resolve(&host, port).await?;
+                    let dns = started.elapsed();
+                    let stream = direct.call(dst).await?;
+                    Ok(UpstreamStream { stream, dns: Some(dns), connect: started.elapsed() - dns, proxied: false })
+                }
+                Route::Via(proxy) => {
+                    debug!("Connecting to {}:{} via parent proxy {}", host, port, proxy.address());
+                    let started = Instant::now();
+                    let stream = connect_via(&proxy, &host, port).await?;
+                    // Ad çözümlemesini üst proxy yapar; tünel kurulumu bağlantı süresine sayılır
+                    Ok(UpstreamStream { stream, dns: None, connect: started.elapsed(), proxied: false })
+                }
+            }
+        })
+    }
+}
+
+/// Opens a raw TCP connection to `authority` (`host:port`) for tunnels, honouring
+/// `[upstream]` the same way the HTTP clients do.
+pub async fn connect(authority: &str) -> Result<TcpStream> {
+    let (host, port) = authority
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host.trim_matches(|c| c == '[' || c == ']'), port.parse::<u16>().ok()?)))
+        .with_context(|| format!("Invalid authority '{}'", authority))?;
+    match ROUTES.route(host, true) {
+        Route::Direct => Ok(resolver::connect(authority).await?),
+        Route::Via(proxy) => {
+            debug!("Tunnelling to {} via parent proxy {}", authority, proxy.address());
+            connect_via(&proxy, host, port).await
+        }
+    }
+}
+
+/// `Proxy-Authorization` for plain HTTP requests that go to an HTTP parent proxy
+/// in absolute form; tunnelled requests authenticate in the `CONNECT` instead.
+pub fn proxy_authorization(uri: &Uri) -> Option<HeaderValue> {
+    if uri.scheme_str() == Some("https") {
+        return None;
+    }
+    let host = uri.host()?.trim_matches(|c| c == '[' || c == ']');
+    let Route::Via(proxy) = ROUTES.route(host, false) else {
+        return None;
+    };
+    if !matches!(proxy.kind, ProxyKind::HttpConnect) {
+        return None;
+    }
+    let (user, pass) = proxy.credentials.as_ref()?;
+    let token = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, pass));
+    HeaderValue::from_str(&format!("Basic {}", token)).ok()
+}
+
+/// A TCP stream to the origin (or a parent proxy) with the time it took to open.
+pub struct UpstreamStream {
+    stream: TcpStream,
+    dns: Option<Duration>,
+    connect: Duration,
+    /// Requests on this stream go to an HTTP parent proxy and use absolute-form targets.
+    proxied: bool,
+}
+
+impl Connection for UpstreamStream {
+    fn connected(&self) -> Connected {
+        self.stream.connected().proxy(self.proxied)
+    }
+}
+
+impl AsyncRead for UpstreamStream {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for UpstreamStream {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>, bufs: &[IoSlice<'_>]) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.stream.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+/// How long opening an upstream connection took. hyper copies it into the extensions
+/// of every response on the connection; only the first response claims it.
+#[derive(Debug, Clone)]
+pub struct ConnectTimings {
+    pub dns: Option<Duration>,
+    pub connect: Duration,
+    pub tls: Option<Duration>,
+    claimed: Arc<AtomicBool>,
+}
+
+impl ConnectTimings {
+    /// `None` if an earlier response on the same connection already took the timings.
+    pub fn claim(&self) -> Option<&Self> {
+        (!self.claimed.swap(true, Ordering::Relaxed)).then_some(self)
+    }
+}
+
+/// Wraps the TLS connector to measure the handshake and attach `ConnectTimings` to the connection.
+#[derive(Clone)]
+pub struct TimedConnector<C>(pub C);
+
+impl<C> Service<Uri> for TimedConnector<C>
+where
+    C: Service<Uri, Response = MaybeHttpsStream<UpstreamStream>>,
+    C::Error: Into<BoxError>,
+    C::Future: Send + 'static,
+{
+    type Response = TimedStream;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<TimedStream, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let started = Instant::now();
+        let connecting = self.0.call(dst);
+        Box::pin(async move {
+            let stream = connecting.await.map_err(Into::into)?;
+            let (tcp, is_tls) = match &stream {
+                MaybeHttpsStream::Http(tcp) => (tcp, false),
+                MaybeHttpsStream::Https(tls) => (tls.get_ref().0, true),
+            };
+            let timings = ConnectTimings {
+                dns: tcp.dns,
+                connect: tcp.connect,
+                tls: is_tls.then(|| started.elapsed().saturating_sub(tcp.dns.unwrap_or_default() + tcp.connect)),
+                claimed: Arc::new(AtomicBool::new(false)),
+            };
+            Ok(TimedStream { stream, timings })
+        })
+    }
+}
+
+pub struct TimedStream {
+    stream: MaybeHttpsStream<UpstreamStream>,
+    timings: ConnectTimings,
+}
+
+impl Connection for TimedStream {
+    fn connected(&self) -> Connected {
+        self.stream.connected().extra(self.timings.clone())
+    }
+}
+
+impl AsyncRead for TimedStream {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TimedStream {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>, bufs: &[IoSlice<'_>]) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.stream.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+async fn connect_to_proxy(proxy: &ParentProxy) -> Result<TcpStream> {
+    let timeout = Duration::from_secs(crate::config::get().downloader.connect_timeout_secs);
+    let stream = tokio::time::timeout(timeout, TcpStream::connect(proxy.address()))
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "connect timeout"))?
+        .with_context(|| format!("Failed to connect to parent proxy {}", proxy.address()))?;
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+
+async fn connect_via(proxy: &ParentProxy, host: &str, port: u16) -> Result<TcpStream> {
+    let timeout = Duration::from_secs(crate::config::get().downloader.connect_timeout_secs);
+    let tunnel = async {
+        let mut stream = connect_to_proxy(proxy).await?;
+        match proxy.kind {
+            ProxyKind::HttpConnect => http_connect(&mut stream, proxy, host, port).await?,
+            ProxyKind::Socks5 => socks5_connect(&mut stream, proxy, host, port).await?,
+        }
+        Ok::<_, anyhow::Error>(stream)
+    };
+    tokio::time::timeout(timeout, tunnel)
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "connect timeout"))?
+        .with_context(|| format!("Parent proxy {} failed", proxy.address()))
+}
+
+async fn http_connect(stream: &mut TcpStream, proxy: &ParentProxy, host: &str, port: u16) -> Result<()> {
+    let authority = if host.contains(':') { format!("[{}]:{}", host, port) } else { format!("{}:{}", host, port) };
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+    if let Some((user, pass)) = &proxy.credentials {
+        let token = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, pass));
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // Yanıtı bayt bayt oku; tünelin ilk baytlarını tüketmemek için başlık sonunda dur.
+    let mut response = Vec::with_capacity(256);
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_CONNECT_RESPONSE {
+            bail!("CONNECT response header too large");
+        }
+        response.push(stream.read_u8().await.context("Connection closed during CONNECT")?);
+    }
+    let status_line = String::from_utf8_lossy(&response);
+    let status_line = status_line.lines().next().unwrap_or_default();
+    match status_line.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => bail!("CONNECT rejected: {}", status_line),
+    }
+}
+
+async fn socks5_connect(stream: &mut TcpStream, proxy: &ParentProxy, host: &str, port: u16) -> Result<()> {
+    let method = if proxy.credentials.is_some() { 0x02 } else { 0x00 };
+    stream.write_all(&[0x05, 0x01, method]).await?;
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice).await?;
+    if choice[1] != method {
+        bail!("SOCKS5 proxy rejected our authentication method");
+    }
+
+    if let Some((user, pass)) = &proxy.credentials {
+        let (Ok(user_len), Ok(pass_len)) = (u8::try_from(user.len()), u8::try_from(pass.len())) else {
+            bail!("SOCKS5 username and password must be at most 255 bytes");
+        };
+        let mut auth = vec![0x01, user_len];
+        auth.extend_from_slice(user.as_bytes());
+        auth.push(pass_len);
+        auth.extend_from_slice(pass.as_bytes());
+        stream.write_all(&auth).await?;
+        let mut status = [0u8; 2];
+        stream.read_exact(&mut status).await?;
+        if status[1] != 0x00 {
+            bail!("SOCKS5 authentication failed");
+        }
+    }
+
+    // Alan adını proxy'nin çözmesi için her zaman ATYP=domain gönder.
+    let host_len = u8::try_from(host.len()).map_err(|_| anyhow::anyhow!("Host name too long for SOCKS5: {}", host))?;
+    let mut request = vec![0x05, 0x01, 0x00, 0x03, host_len];
+    request.extend_from_slice(host.as_bytes());
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0x00 {
+        bail!("SOCKS5 CONNECT failed with reply code {}", reply[1]);
+    }
+    let bound_len = match reply[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => stream.read_u8().await? as usize,
+        other => bail!("SOCKS5 reply has unknown address type {}", other),
+    };
+    let mut bound = vec![0u8; bound_len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_proxy_bare_entry_matches_subdomains() {
+        assert!(no_proxy_matches("example.com", "example.com"));
+        assert!(no_proxy_matches("example.com", "api.example.com"));
+        assert!(!no_proxy_matches("example.com", "notexample.com"));
+        assert!(!no_proxy_matches("example.com", "example.com.evil"));
+    }
+
+    #[test]
+    fn no_proxy_dot_and_wildcard_entries() {
+        assert!(no_proxy_matches(".internal.example", "internal.example"));
+        assert!(no_proxy_matches(".internal.example", "db.internal.example"));
+        assert!(no_proxy_matches("*.corp", "git.corp"));
+        assert!(!no_proxy_matches("*.corp", "corp.example"));
+        assert!(no_proxy_matches("*", "anything"));
+    }
+}
@@ -0,0 +1,159 @@
+// File: crates/service/src/upstream_tls.rs
+
+use anyhow::{bail, Context, Result};
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerName};
+use rustls_pemfile::Item;
+use sentiric_core::UpstreamTls;
+use std::error::Error as StdError;
+use std::sync::{Arc, OnceLock};
+use std::time::SystemTime;
+use tracing::{info, warn};
+use wildmatch::WildMatch;
+
+static POLICY: OnceLock<TlsPolicy> = OnceLock::new();
+
+/// Origin TLS verification: system roots plus `ca_bundle`, per-domain exceptions and client certificates.
+struct TlsPolicy {
+    verifier: Arc<PolicyVerifier>,
+    client_certs: Vec<(WildMatch, Vec<Certificate>, PrivateKey)>,
+}
+
+/// Loads the CA bundle and client certificates so configuration mistakes surface at startup.
+pub fn init(settings: &UpstreamTls) -> Result<()> {
+    let mut roots = RootCertStore::empty();
+    match rustls_native_certs::load_native_certs() {
+        Ok(certs) => {
+            let der: Vec<Vec<u8>> = certs.into_iter().map(|c| c.0).collect();
+            roots.add_parsable_certificates(&der);
+        }
+        Err(e) => warn!("Could not load system root certificates: {}", e),
+    }
+    if let Some(path) = &settings.ca_bundle {
+        let bundle = read_pem(path)?;
+        let mut added = 0;
+        for item in bundle {
+            if let Item::X509Certificate(der) = item {
+                roots.add(&Certificate(der)).with_context(|| format!("Invalid CA certificate in '{}'", path))?;
+                added += 1;
+            }
+        }
+        if added == 0 {
+            bail!("CA bundle '{}' contains no certificates", path);
+        }
+        info!("Trusting {} extra CA certificate(s) from {}", added, path);
+    }
+
+    for domain in &settings.insecure_domains {
+        warn!("⚠️ Upstream certificate verification is DISABLED for '{}'", domain);
+    }
+
+    let client_certs = settings
+        .client_certs
+        .iter()
+        .map(|entry| {
+            let mut chain = Vec::new();
+            for item in read_pem(&entry.cert)? {
+                if let Item::X509Certificate(der) = item {
+                    chain.push(Certificate(der));
+                }
+            }
+            let key = read_pem(&entry.key)?
+                .into_iter()
+                .find_map(|item| match item {
+                    Item::PKCS8Key(der) | Item::RSAKey(der) | Item::ECKey(der) => Some(PrivateKey(der)),
+                    _ => None,
+                })
+                .with_context(|| format!("No private key found in '{}'", entry.key))?;
+            if chain.is_empty() {
+                bail!("No certificate found in '{}'", entry.cert);
+            }
+            // Anahtar/sertifika uyumsuzluğunu ilk istekte değil, açılışta yakala
+            ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(RootCertStore::empty())
+                .with_client_auth_cert(chain.clone(), key.clone())
+                .with_context(|| format!("Invalid client certificate for '{}'", entry.domain))?;
+            info!("Presenting client certificate {} to {}", entry.cert, entry.domain);
+            Ok((WildMatch::new(&entry.domain), chain, key))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let verifier = PolicyVerifier {
+        webpki: WebPkiVerifier::new(roots, None),
+        insecure: settings.insecure_domains.iter().map(|d| WildMatch::new(d)).collect(),
+    };
+    let policy = TlsPolicy { verifier: Arc::new(verifier), client_certs };
+    POLICY.set(policy).map_err(|_| anyhow::anyhow!("Upstream TLS policy already initialized"))?;
+    Ok(())
+}
+
+fn policy() -> &'static TlsPolicy {
+    POLICY.get().expect("Upstream TLS policy is not initialized")
+}
+
+/// Index of the client certificate configured for `host`, if any.
+pub fn client_cert_for(host: &str) -> Option<usize> {
+    policy().client_certs.iter().position(|(pattern, _, _)| pattern.matches(host))
+}
+
+/// rustls configuration for origin connections, presenting the client certificate at `client_cert`.
+pub fn client_config(client_cert: Option<usize>) -> ClientConfig {
+    let policy = policy();
+    let builder = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(policy.verifier.clone());
+    match client_cert.and_then(|index| policy.client_certs.get(index)) {
+        Some((_, chain, key)) => builder
+            .with_client_auth_cert(chain.clone(), key.clone())
+            .expect("client certificate was validated at startup"),
+        None => builder.with_no_client_auth(),
+    }
+}
+
+/// The certificate problem behind a failed upstream request, if that is why it failed.
+pub fn certificate_error(err: &(dyn StdError + 'static)) -> Option<String> {
+    crate::downloader::error_chain(err).into_iter().find_map(|e| match e.downcast_ref::<rustls::Error>()? {
+        rustls::Error::InvalidCertificate(reason) => Some(format!("{:?}", reason)),
+        _ => None,
+    })
+}
+
+fn read_pem(path: &str) -> Result<Vec<Item>> {
+    let content = std::fs::read(path).with_context(|| format!("Failed to read '{}'", path))?;
+    rustls_pemfile::read_all(&mut content.as_slice()).with_context(|| format!("Invalid PEM file '{}'", path))
+}
+
+/// Web PKI verification that lets `insecure_domains` through with a warning.
+struct PolicyVerifier {
+    webpki: WebPkiVerifier,
+    insecure: Vec<WildMatch>,
+}
+
+impl ServerCertVerifier for PolicyVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        match self.webpki.verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now) {
+            Err(rustls::Error::InvalidCertificate(reason)) => {
+                let host = match server_name {
+                    ServerName::DnsName(name) => name.as_ref().to_string(),
+                    ServerName::IpAddress(ip) => ip.to_string(),
+                    _ => return Err(rustls::Error::InvalidCertificate(reason)),
+                };
+                if !self.insecure.iter().any(|pattern| pattern.matches(&host)) {
+                    return Err(rustls::Error::InvalidCertificate(reason));
+                }
+                warn!("⚠️ Accepting invalid certificate for {} ({:?}) because it is listed in upstream_tls.insecure_domains", host, reason);
+                Ok(ServerCertVerified::assertion())
+            }
+            result => result,
+        }
+    }
+}
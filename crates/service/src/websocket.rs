@@ -0,0 +1,400 @@
+// File: crates/service/src/websocket.rs
+
+use crate::downloader;
+use crate::flows;
+use crate::management::{EVENT_BROADCASTER, WsEvent};
+use crate::shutdown;
+use anyhow::Result;
+use hyper::{header, upgrade, Body, Request, Response, StatusCode};
+use sentiric_core::{FlowEntry, FrameDirection, WebSocketFrame, WebSocketSummary};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tracing::{debug, error, info};
+
+/// Payload bytes kept per frame when frame inspection is enabled.
+const FRAME_PREVIEW_BYTES: usize = 256;
+
+/// Returns true for an HTTP/1.1 `Upgrade: websocket` handshake.
+pub fn is_upgrade_request(req: &Request<Body>) -> bool {
+    let has_token = |name: header::HeaderName, token: &str| {
+        req.headers()
+            .get_all(name)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .any(|v| v.trim().eq_ignore_ascii_case(token))
+    };
+    has_token(header::CONNECTION, "upgrade") && has_token(header::UPGRADE, "websocket")
+}
+
+/// Forwards the handshake to the origin and, once both sides have switched
+/// protocols, splices the two connections while counting frames. `flow` gets a
+/// WebSocket summary once the upgrade succeeds; the tunnel then publishes it.
+pub async fn serve(mut req: Request<Body>, flow: &mut FlowEntry) -> Result<Response<Body>> {
+    let client_upgrade = upgrade::on(&mut req);
+
+    let mut response = downloader::forward_upgrade(req).await?;
+    flows::record_upstream(flow, &response);
+    if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+        debug!("Origin declined WebSocket upgrade for {} with {}", flow.uri, response.status());
+        return Ok(response);
+    }
+    let origin_upgrade = upgrade::on(&mut response);
+
+    info!("[WEBSOCKET] {}", flow.uri);
+    flow.status_code = StatusCode::SWITCHING_PROTOCOLS.as_u16();
+    flow.response_headers = flows::headers(response.headers());
+    flow.websocket = Some(WebSocketSummary::default());
+    flow.timings.ttfb_ms = Some(elapsed_ms(flow));
+    let flow = flow.clone();
+    let _ = EVENT_BROADCASTER.send(WsEvent::FlowUpdated { flow: Box::new(flow.clone()) });
+
+    tokio::spawn(async move {
+        let _guard = shutdown::track();
+        match tokio::try_join!(client_upgrade, origin_upgrade) {
+            Ok((client, origin)) => tunnel(client, origin, flow).await,
+            Err(e) => error!(cause = ?e, "WebSocket upgrade error"),
+        }
+    });
+
+    Ok(response)
+}
+
+#[derive(Default)]
+struct DirectionCounters {
+    frames: AtomicU64,
+    bytes: AtomicU64,
+}
+
+async fn tunnel(client: upgrade::Upgraded, origin: upgrade::Upgraded, mut flow: FlowEntry) {
+    let inspect = crate::config::get().proxy.inspect_websocket_frames;
+    let from_client = Arc::new(DirectionCounters::default());
+    let from_server = Arc::new(DirectionCounters::default());
+
+    let (client_read, client_write) = tokio::io::split(client);
+    let (origin_read, origin_write) = tokio::io::split(origin);
+
+    let upstream = pump(client_read, origin_write, FrameDirection::ClientToServer, flow.id.clone(), inspect, from_client.clone());
+    let downstream = pump(origin_read, client_write, FrameDirection::ServerToClient, flow.id.clone(), inspect, from_server.clone());
+    let splice = async { tokio::join!(upstream, downstream) };
+    tokio::pin!(splice);
+
+    // Açık bağlantılar için akış kaydını saniyede bir güncelle.
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    ticker.tick().await;
+    loop {
+        tokio::select! {
+            _ = &mut splice => break,
+            _ = shutdown::requested() => break,
+            _ = ticker.tick() => {
+                flow.websocket = Some(summary(&from_client, &from_server, false));
+                let _ = EVENT_BROADCASTER.send(WsEvent::FlowUpdated { flow: Box::new(flow.clone()) });
+            }
+        }
+    }
+
+    let closed = summary(&from_client, &from_server, true);
+    debug!(
+        "WebSocket closed: {} ({} frames from client, {} frames from server)",
+        flow.uri, closed.frames_from_client, closed.frames_from_server
+    );
+    flow.websocket = Some(closed);
+    flow.timings.total_ms = Some(elapsed_ms(&flow));
+    let _ = EVENT_BROADCASTER.send(WsEvent::FlowUpdated { flow: Box::new(flow) });
+}
+
+fn elapsed_ms(flow: &FlowEntry) -> f64 {
+    flows::now_ms().saturating_sub(flow.started_at_ms) as f64
+}
+
+fn summary(from_client: &DirectionCounters, from_server: &DirectionCounters, closed: bool) -> WebSocketSummary {
+    WebSocketSummary {
+        frames_from_client: from_client.frames.load(Ordering::Relaxed),
+        frames_from_server: from_server.frames.load(Ordering::Relaxed),
+        bytes_from_client: from_client.bytes.load(Ordering::Relaxed),
+        bytes_from_server: from_server.bytes.load(Ordering::Relaxed),
+        closed,
+    }
+}
+
+/// Copies one direction of the tunnel, feeding every byte through a frame parser.
+async fn pump<R, W>(
+    mut reader: R,
+    mut writer: W,
+    direction: FrameDirection,
+    flow_id: String,
+    inspect: bool,
+    counters: Arc<DirectionCounters>,
+) where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut parser = FrameParser::new(inspect);
+    let mut buf = vec![0u8; 16 * 1024];
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        if writer.write_all(&buf[..n]).await.is_err() {
+            break;
+        }
+        counters.bytes.fetch_add(n as u64, Ordering::Relaxed);
+        for frame in parser.feed(&buf[..n]) {
+            counters.frames.fetch_add(1, Ordering::Relaxed);
+            if inspect {
+                let _ = EVENT_BROADCASTER.send(WsEvent::WebSocketFrame {
+                    frame: frame.into_event(&flow_id, direction),
+                });
+            }
+        }
+    }
+    // Karşı tarafa yazma yönünün bittiğini bildir.
+    let _ = writer.shutdown().await;
+}
+
+/// A frame whose header and payload have both passed through the tunnel.
+struct ParsedFrame {
+    fin: bool,
+    opcode: u8,
+    length: u64,
+    preview: Vec<u8>,
+}
+
+impl ParsedFrame {
+    fn into_event(self, flow_id: &str, direction: FrameDirection) -> WebSocketFrame {
+        let opcode = match self.opcode {
+            0x0 => "continuation",
+            0x1 => "text",
+            0x2 => "binary",
+            0x8 => "close",
+            0x9 => "ping",
+            0xA => "pong",
+            _ => "reserved",
+        };
+        let preview = (self.opcode == 0x1 || self.opcode == 0x8)
+            .then(|| String::from_utf8_lossy(&self.preview).into_owned());
+        WebSocketFrame {
+            flow_id: flow_id.to_string(),
+            direction,
+            opcode: opcode.to_string(),
+            fin: self.fin,
+            length: self.length,
+            preview,
+        }
+    }
+}
+
+enum ParseState {
+    Header,
+    Payload { remaining: u64 },
+}
+
+/// Incremental RFC 6455 frame parser; frames may be split across reads arbitrarily.
+struct FrameParser {
+    keep_preview: bool,
+    state: ParseState,
+    header: Vec<u8>,
+    current: Option<ParsedFrame>,
+    mask: Option<[u8; 4]>,
+    offset: u64,
+}
+
+impl FrameParser {
+    fn new(keep_preview: bool) -> Self {
+        Self {
+            keep_preview,
+            state: ParseState::Header,
+            header: Vec::with_capacity(14),
+            current: None,
+            mask: None,
+            offset: 0,
+        }
+    }
+
+    fn feed(&mut self, mut data: &[u8]) -> Vec<ParsedFrame> {
+        let mut frames = Vec::new();
+        while !data.is_empty() {
+            match self.state {
+                ParseState::Header => {
+                    self.header.push(data[0]);
+                    data = &data[1..];
+                    if let Some(frame) = self.try_finish_header() {
+                        frames.push(frame);
+                    }
+                }
+                ParseState::Payload { remaining } => {
+                    let take = remaining.min(data.len() as u64) as usize;
+                    self.capture_preview(&data[..take]);
+                    data = &data[take..];
+                    let remaining = remaining - take as u64;
+                    if remaining == 0 {
+                        self.state = ParseState::Header;
+                        frames.extend(self.current.take());
+                    } else {
+                        self.state = ParseState::Payload { remaining };
+                    }
+                }
+            }
+        }
+        frames
+    }
+
+    /// Parses the header once enough bytes are buffered. Returns the frame
+    /// right away if it carries no payload.
+    fn try_finish_header(&mut self) -> Option<ParsedFrame> {
+        let h = &self.header;
+        if h.len() < 2 {
+            return None;
+        }
+        let masked = h[1] & 0x80 != 0;
+        let extended = match h[1] & 0x7F {
+            126 => 2,
+            127 => 8,
+            _ => 0,
+        };
+        let needed = 2 + extended + if masked { 4 } else { 0 };
+        if h.len() < needed {
+            return None;
+        }
+
+        let length = match extended {
+            2 => u16::from_be_bytes([h[2], h[3]]) as u64,
+            8 => u64::from_be_bytes(h[2..10].try_into().unwrap()),
+            _ => (h[1] & 0x7F) as u64,
+        };
+        self.mask = masked.then(|| h[needed - 4..needed].try_into().unwrap());
+        self.offset = 0;
+        let frame = ParsedFrame {
+            fin: h[0] & 0x80 != 0,
+            opcode: h[0] & 0x0F,
+            length,
+            preview: Vec::new(),
+        };
+        self.header.clear();
+
+        if length == 0 {
+            return Some(frame);
+        }
+        self.current = Some(frame);
+        self.state = ParseState::Payload { remaining: length };
+        None
+    }
+
+    fn capture_preview(&mut self, chunk: &[u8]) {
+        let start = self.offset;
+        self.offset += chunk.len() as u64;
+        if !self.keep_preview {
+            return;
+        }
+        let Some(frame) = self.current.as_mut() else { return };
+        let room = FRAME_PREVIEW_BYTES.saturating_sub(frame.preview.len());
+        for (i, byte) in chunk.iter().take(room).enumerate() {
+            let key = self.mask.map_or(0, |m| m[((start + i as u64) % 4) as usize]);
+            frame.preview.push(byte ^ key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(fin: bool, opcode: u8, mask: Option<[u8; 4]>, payload: &[u8]) -> Vec<u8> {
+        let mut out = vec![(if fin { 0x80 } else { 0 }) | opcode];
+        let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+        match payload.len() {
+            n if n < 126 => out.push(mask_bit | n as u8),
+            n if n <= u16::MAX as usize => {
+                out.push(mask_bit | 126);
+                out.extend_from_slice(&(n as u16).to_be_bytes());
+            }
+            n => {
+                out.push(mask_bit | 127);
+                out.extend_from_slice(&(n as u64).to_be_bytes());
+            }
+        }
+        match mask {
+            Some(m) => {
+                out.extend_from_slice(&m);
+                out.extend(payload.iter().enumerate().map(|(i, b)| b ^ m[i % 4]));
+            }
+            None => out.extend_from_slice(payload),
+        }
+        out
+    }
+
+    #[test]
+    fn parses_unmasked_text_frame() {
+        let mut parser = FrameParser::new(true);
+        let frames = parser.feed(&frame(true, 0x1, None, b"hello"));
+        assert_eq!(frames.len(), 1);
+        assert!(frames[0].fin);
+        assert_eq!(frames[0].opcode, 0x1);
+        assert_eq!(frames[0].length, 5);
+        assert_eq!(frames[0].preview, b"hello");
+    }
+
+    #[test]
+    fn unmasks_client_payload() {
+        let mut parser = FrameParser::new(true);
+        let frames = parser.feed(&frame(true, 0x1, Some([0x37, 0xfa, 0x21, 0x3d]), b"masked text"));
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].preview, b"masked text");
+    }
+
+    #[test]
+    fn handles_16_bit_length() {
+        let payload = vec![b'a'; 300];
+        let mut parser = FrameParser::new(true);
+        let frames = parser.feed(&frame(true, 0x2, None, &payload));
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].length, 300);
+        assert_eq!(frames[0].preview.len(), FRAME_PREVIEW_BYTES);
+    }
+
+    #[test]
+    fn handles_64_bit_length() {
+        let payload = vec![0u8; 70_000];
+        let mut parser = FrameParser::new(false);
+        let frames = parser.feed(&frame(true, 0x2, Some([1, 2, 3, 4]), &payload));
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].length, 70_000);
+        assert!(frames[0].preview.is_empty());
+    }
+
+    #[test]
+    fn reassembles_frames_split_across_reads() {
+        let bytes = frame(true, 0x1, Some([9, 8, 7, 6]), b"split across many reads");
+        let mut parser = FrameParser::new(true);
+        let mut frames = Vec::new();
+        for byte in &bytes {
+            frames.extend(parser.feed(std::slice::from_ref(byte)));
+        }
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].preview, b"split across many reads");
+    }
+
+    #[test]
+    fn reports_fragments_and_control_frames() {
+        let mut bytes = frame(false, 0x1, None, b"first ");
+        bytes.extend(frame(true, 0x9, None, b""));
+        bytes.extend(frame(true, 0x0, None, b"second"));
+        let mut parser = FrameParser::new(true);
+        let frames = parser.feed(&bytes);
+        let kinds: Vec<_> = frames.iter().map(|f| (f.fin, f.opcode, f.length)).collect();
+        assert_eq!(kinds, vec![(false, 0x1, 6), (true, 0x9, 0), (true, 0x0, 6)]);
+    }
+
+    #[test]
+    fn names_opcodes_in_events() {
+        let mut parser = FrameParser::new(true);
+        let mut frames = parser.feed(&frame(true, 0x2, None, b"\x00\x01"));
+        let event = frames.remove(0).into_event("flow-1", FrameDirection::ServerToClient);
+        assert_eq!(event.opcode, "binary");
+        assert_eq!(event.length, 2);
+        assert!(event.preview.is_none());
+    }
+}
@@ -0,0 +1,24 @@
+#!/bin/bash
+set -e
+
+. "$(dirname "$0")/../helpers.sh"
+
+TEST_URL_CACHE="http://cachefly.cachefly.net/10mb.test"
+FIRST_OUTPUT="/tmp/concurrent_fill_first.bin"
+SECOND_OUTPUT="/tmp/concurrent_fill_second.bin"
+
+print_header "SCENARIO 09: Cache (HIT while the fill is in progress)"
+capture_initial_stats
+
+print_step "Starting the first request in the background (CACHE MISS)"
+run_proxied_curl ${TEST_URL_CACHE} -o ${FIRST_OUTPUT} &
+FIRST_PID=$!
+sleep 0.5
+
+print_step "Second request joins the in-progress fill (should be a CACHE HIT)"
+assert_success "Second request completed" run_proxied_curl ${TEST_URL_CACHE} -o ${SECOND_OUTPUT}
+assert_success "First request completed" wait ${FIRST_PID}
+
+assert_success "Both clients received identical bodies" cmp -s ${FIRST_OUTPUT} ${SECOND_OUTPUT}
+assert_stats_increment 1 1 "after concurrent requests"
+rm -f ${FIRST_OUTPUT} ${SECOND_OUTPUT}
@@ -5,6 +5,23 @@ export interface CacheStats {
   diskItems: number;
   totalDiskSizeBytes: number;
   bytesSaved: number;
+  timeSavedMs: number;
+  upstream: UpstreamStats[];
+  rejectedClients: AccessRejections;
+}
+
+export interface AccessRejections {
+  proxy: number;
+  dns: number;
+  management: number;
+}
+
+export interface UpstreamStats {
+  domain: string;
+  fills: number;
+  bytesDownloaded: number;
+  avgLatencyMs: number;
+  avgThroughputBytesPerSec: number;
 }
 
 export interface CacheEntry {
@@ -19,9 +36,100 @@ export interface FlowEntry {
   statusCode: number;
   responseSizeBytes: number;
   isHit: boolean;
+  protocol: string;
+  websocket?: WebSocketSummary;
+  tlsPassthrough?: PassthroughReason;
+  user?: string;
+  error?: string;
+  mock?: MockSummary;
+  replayed?: boolean;
+  upstreamProtocol?: string;
+  clientIp?: string;
+  startedAtMs: number;
+  requestHeaders?: FlowHeader[];
+  responseHeaders?: FlowHeader[];
+  timings: FlowTimings;
+  cache?: CacheDecision;
+  rule?: string;
+  requestBody?: CapturedBody;
+  responseBody?: CapturedBody;
+}
+
+export interface FlowHeader {
+  name: string;
+  value: string;
+}
+
+export interface FlowTimings {
+  dnsMs?: number;
+  connectMs?: number;
+  tlsMs?: number;
+  ttfbMs?: number;
+  totalMs?: number;
+}
+
+export interface CacheDecision {
+  outcome: 'hit' | 'miss' | 'bypass';
+  reason: string;
 }
 
-export type Action = 'Allow' | 'Block' | 'BypassCache';
+export interface CapturedBody {
+  size: number;
+  truncated: boolean;
+  text?: string;
+  encoding?: 'base64';
+}
+
+export interface MockSummary {
+  rule: string;
+  failureInjected: boolean;
+}
+
+export type PassthroughReason = { kind: 'rule'; name: string } | { kind: 'learned' };
+
+export interface WebSocketSummary {
+  framesFromClient: number;
+  framesFromServer: number;
+  bytesFromClient: number;
+  bytesFromServer: number;
+  closed: boolean;
+}
+
+export interface WebSocketFrame {
+  flowId: string;
+  direction: 'clientToServer' | 'serverToClient';
+  opcode: string;
+  fin: boolean;
+  length: number;
+  preview: string | null;
+}
+
+export interface HeaderEdits {
+  remove?: string[];
+  set?: Record<string, string>;
+  add?: Record<string, string>;
+}
+
+export type Action =
+  | 'Allow'
+  | 'Block'
+  | 'BypassCache'
+  | 'Tunnel'
+  | { throttle: number }
+  | { 'rewrite-headers': { request: HeaderEdits; response: HeaderEdits } }
+  | { 'map-remote': string }
+  | { 'map-local': string }
+  | { mock: MockResponse };
+
+export interface MockResponse {
+  status: number;
+  headers?: Record<string, string>;
+  body?: string;
+  body_file?: string;
+  delay_ms: number;
+  failure_rate: number;
+  failure_status: number;
+}
 
 // API'den gelen `url-pattern` ile eşleşmesi için.
 export type RuleCondition = { domain: string } | { "url-pattern": string };
@@ -30,11 +138,13 @@ export interface Rule {
   name: string;
   condition: RuleCondition;
   action: Action;
+  users?: string[];
 }
 
 export type WsEvent =
   | { type: 'statsUpdated'; stats: CacheStats }
-  | { type: 'flowUpdated'; flow: FlowEntry };
+  | { type: 'flowUpdated'; flow: FlowEntry }
+  | { type: 'webSocketFrame'; frame: WebSocketFrame };
 
 // --- NİHAİ DÜZELTME: ADRESLERİ HER ZAMAN MUTLAK OLARAK TANIMLA ---
 // Backend'imiz her zaman 8080 portunda çalışır. Bu değişmez bir kuraldır.
@@ -65,9 +175,54 @@ export async function fetchRules(): Promise<Rule[]> {
   return response.json();
 }
 
+export interface BucketStatus {
+  key: string;
+  bytesPerSec: number;
+  bytesTransferred: number;
+  delayedMs: number;
+}
+
+export interface HarStatus {
+  recording: boolean;
+  recordedEntries: number;
+  replay: { mode: 'strict' | 'fallback'; entries: number; served: number } | null;
+}
+
+export async function fetchHarStatus(): Promise<HarStatus> {
+  const response = await fetch(`${API_BASE_URL}/har/status`);
+  if (!response.ok) throw new Error('Failed to fetch HAR status');
+  return response.json();
+}
+
+export async function setHarRecording(enabled: boolean): Promise<HarStatus> {
+  const response = await fetch(`${API_BASE_URL}/har/recording`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ enabled }),
+  });
+  if (!response.ok) throw new Error('Failed to toggle HAR recording');
+  return response.json();
+}
+
+export const harExportUrl = `${API_BASE_URL}/har`;
+
+export interface ThrottleStatus {
+  global: BucketStatus | null;
+  clients: BucketStatus[];
+  domains: BucketStatus[];
+  rules: BucketStatus[];
+}
+
+export async function fetchThrottle(): Promise<ThrottleStatus> {
+  const response = await fetch(`${API_BASE_URL}/throttle`);
+  if (!response.ok) throw new Error('Failed to fetch throttle status');
+  return response.json();
+}
+
 interface EventStreamCallbacks {
   onStatsUpdated?: (stats: CacheStats) => void;
   onFlowUpdated?: (flow: FlowEntry) => void;
+  onWebSocketFrame?: (frame: WebSocketFrame) => void;
   onOpen?: () => void;
   onClose?: () => void;
 }
@@ -90,6 +245,8 @@ function connect(callbacks: EventStreamCallbacks) {
         callbacks.onStatsUpdated?.(parsedEvent.stats);
       } else if (parsedEvent.type === 'flowUpdated') {
         callbacks.onFlowUpdated?.(parsedEvent.flow);
+      } else if (parsedEvent.type === 'webSocketFrame') {
+        callbacks.onWebSocketFrame?.(parsedEvent.frame);
       }
     } catch (e) { console.error("Failed to parse event:", e); }
   };
@@ -10,6 +10,15 @@ function formatBytes(bytes: number) {
   return parseFloat((bytes / Math.pow(k, i)).toFixed(2)) + ' ' + sizes[i];
 }
 
+function formatDuration(ms: number) {
+  if (ms < 1000) return `${ms} ms`;
+  const seconds = ms / 1000;
+  if (seconds < 60) return `${seconds.toFixed(1)} sn`;
+  const minutes = seconds / 60;
+  if (minutes < 60) return `${minutes.toFixed(1)} dk`;
+  return `${(minutes / 60).toFixed(1)} sa`;
+}
+
 const StatCard = ({ title, value, color }: { title: string; value: string | number; color?: string }) => (
   <div class="stat-card">
     <div class="stat-title">{title}</div>
@@ -27,10 +36,12 @@ export function Dashboard() {
       <div class="stats-grid">
         <StatCard title="Bağlantı" value={isConnected.value ? 'Aktif' : 'Kesildi'} color={isConnected.value ? '#4ade80' : '#f87171'} />
         <StatCard title="Tasarruf Edilen Veri" value={formatBytes(s.bytesSaved)} color="#34d399" />
+        <StatCard title="Kurtarılan Zaman" value={formatDuration(s.timeSavedMs)} color="#34d399" />
         <StatCard title="Hit Oranı" value={hitRate} />
         <StatCard title="Toplam İstek" value={s.totalRequests} />
         <StatCard title="Cache Boyutu" value={formatBytes(s.totalDiskSizeBytes)} />
         <StatCard title="Cache Girdileri" value={s.diskItems} />
+        <StatCard title="Reddedilen İstemciler" value={s.rejectedClients.proxy + s.rejectedClients.dns + s.rejectedClients.management} />
       </div>
       
       {/* 
@@ -1,4 +1,7 @@
-import { flows } from '../store';
+import { useState, useEffect } from 'preact/hooks';
+import { flows, wsFrames } from '../store';
+import * as api from '../api';
+import type { CapturedBody, FlowEntry, FlowHeader, HarStatus } from '../api';
 
 function formatBytes(bytes: number) {
   if (bytes === 0) return '0 B';
@@ -17,8 +20,8 @@ const StatusBadge = ({ code }: { code: number }) => {
     return <span style={{ color, fontWeight: 'bold' }}>{code}</span>;
 }
 
-const HitBadge = ({ isHit }: { isHit: boolean }) => (
-    <span style={{ 
+const HitBadge = ({ isHit, title }: { isHit: boolean; title?: string }) => (
+    <span title={title} style={{ 
         background: isHit ? '#d4edda' : '#f8d7da',
         color: isHit ? '#155724' : '#721c24',
         padding: '2px 6px',
@@ -30,36 +33,169 @@ const HitBadge = ({ isHit }: { isHit: boolean }) => (
     </span>
 )
 
+const formatMs = (ms?: number) => ms === undefined ? '—' : `${ms.toFixed(1)} ms`;
+
+const CacheCell = ({ flow }: { flow: FlowEntry }) => {
+    if (flow.tlsPassthrough) {
+        const reason = flow.tlsPassthrough.kind === 'rule' ? `Kural: ${flow.tlsPassthrough.name}` : 'Öğrenildi';
+        return <span title={reason}>TÜNEL ({reason})</span>;
+    }
+    if (flow.replayed) return <span title="HAR dosyasından">TEKRAR</span>;
+    if (flow.mock) {
+        return <span title={`Kural: ${flow.mock.rule}`}>SAHTE{flow.mock.failureInjected ? ' (hata enjekte)' : ''}</span>;
+    }
+    if (!flow.websocket) {
+        if (flow.cache?.outcome === 'bypass') return <span title={flow.cache.reason}>BYPASS</span>;
+        return <HitBadge isHit={flow.isHit} title={flow.cache?.reason} />;
+    }
+    const ws = flow.websocket;
+    return (
+        <span title={`${formatBytes(ws.bytesFromClient)} ↑ / ${formatBytes(ws.bytesFromServer)} ↓`}>
+            WS {ws.framesFromClient} ↑ / {ws.framesFromServer} ↓{ws.closed ? ' (kapandı)' : ''}
+        </span>
+    );
+}
+
+const FrameList = ({ flowId }: { flowId: string }) => {
+    const frames = wsFrames.value.filter(f => f.flowId === flowId);
+    return (
+        <div class="section">
+            <h2>WebSocket Çerçeveleri</h2>
+            {frames.length === 0 ? (
+                <p style={{ color: '#6c757d' }}>Bu bağlantı için çerçeve yok. Çerçeve incelemesi için yapılandırmada <code>inspect_websocket_frames</code> açık olmalı.</p>
+            ) : (
+                <table>
+                    <thead>
+                        <tr><th>Yön</th><th>Tür</th><th>Boyut</th><th>İçerik</th></tr>
+                    </thead>
+                    <tbody>
+                        {frames.map((frame, i) => (
+                            <tr key={i}>
+                                <td>{frame.direction === 'clientToServer' ? '↑ İstemci' : '↓ Sunucu'}</td>
+                                <td>{frame.opcode}{frame.fin ? '' : ' (devamı var)'}</td>
+                                <td>{formatBytes(frame.length)}</td>
+                                <td class="url-cell" title={frame.preview ?? ''}>{frame.preview ?? '—'}</td>
+                            </tr>
+                        ))}
+                    </tbody>
+                </table>
+            )}
+        </div>
+    );
+}
+
+const HeaderTable = ({ title, headers }: { title: string; headers?: FlowHeader[] }) => (
+    <div>
+        <h3>{title}</h3>
+        {!headers || headers.length === 0 ? <p style={{ color: '#6c757d' }}>—</p> : (
+            <table>
+                <tbody>
+                    {headers.map((header, i) => (
+                        <tr key={i}><td style={{ fontWeight: 'bold' }}>{header.name}</td><td class="url-cell">{header.value}</td></tr>
+                    ))}
+                </tbody>
+            </table>
+        )}
+    </div>
+);
+
+const BodyView = ({ title, body }: { title: string; body?: CapturedBody }) => {
+    if (!body) return null;
+    return (
+        <div>
+            <h3>{title} ({formatBytes(body.size)}{body.truncated ? ', kırpıldı' : ''})</h3>
+            {body.text === undefined
+                ? <p style={{ color: '#6c757d' }}>İçerik gösterilemiyor (sıkıştırılmış gövde sınırı aştı).</p>
+                : <pre style={{ maxHeight: '300px', overflow: 'auto', whiteSpace: 'pre-wrap', wordBreak: 'break-all' }}>
+                    {body.encoding === 'base64' ? `[base64] ${body.text}` : body.text}
+                  </pre>}
+        </div>
+    );
+}
+
+const FlowDetails = ({ flow }: { flow: FlowEntry }) => {
+    const t = flow.timings;
+    return (
+        <div class="section">
+            <h2>Akış Ayrıntıları</h2>
+            <p class="url-cell">{flow.method} {flow.uri}</p>
+            <table>
+                <tbody>
+                    <tr><td>İstemci</td><td>{flow.clientIp ?? '—'}{flow.user ? ` (${flow.user})` : ''}</td></tr>
+                    <tr><td>Başlangıç</td><td>{flow.startedAtMs ? new Date(flow.startedAtMs).toLocaleString() : '—'}</td></tr>
+                    <tr><td>Protokol</td><td>{flow.protocol}{flow.upstreamProtocol ? ` → origin ${flow.upstreamProtocol}` : ''}</td></tr>
+                    <tr><td>Kural</td><td>{flow.rule ?? '—'}</td></tr>
+                    <tr><td>Önbellek</td><td>{flow.cache ? `${flow.cache.outcome.toUpperCase()} · ${flow.cache.reason}` : '—'}</td></tr>
+                    <tr>
+                        <td>Zamanlama</td>
+                        <td>DNS {formatMs(t.dnsMs)} · Bağlantı {formatMs(t.connectMs)} · TLS {formatMs(t.tlsMs)} · İlk bayt {formatMs(t.ttfbMs)} · Toplam {formatMs(t.totalMs)}</td>
+                    </tr>
+                </tbody>
+            </table>
+            <HeaderTable title="İstek Başlıkları" headers={flow.requestHeaders} />
+            <HeaderTable title="Yanıt Başlıkları" headers={flow.responseHeaders} />
+            <BodyView title="İstek Gövdesi" body={flow.requestBody} />
+            <BodyView title="Yanıt Gövdesi" body={flow.responseBody} />
+        </div>
+    );
+}
+
+const HarControls = () => {
+    const [status, setStatus] = useState<HarStatus | null>(null);
+    useEffect(() => { api.fetchHarStatus().then(setStatus).catch(() => setStatus(null)); }, []);
+    if (!status) return null;
+    const toggle = () => api.setHarRecording(!status.recording).then(setStatus);
+    return (
+        <p style={{ marginTop: 0 }}>
+            <button onClick={toggle}>{status.recording ? 'HAR kaydını durdur' : 'HAR kaydını başlat'}</button>
+            {' '}<a href={api.harExportUrl}>HAR indir ({status.recordedEntries} kayıt)</a>
+            {status.replay && <span style={{ color: '#6c757d' }}> · Tekrar oynatma: {status.replay.mode}, {status.replay.served}/{status.replay.entries}</span>}
+        </p>
+    );
+}
+
 export function NetworkFlow() {
+  const [selectedId, setSelectedId] = useState<string | null>(null);
+  const selected = flows.value.find(flow => flow.id === selectedId);
   return (
     <div>
         <h1>Ağ Akışı İnceleyici</h1>
         <div class="section">
             <p style={{marginTop: 0, color: '#6c757d'}}>Sisteminizden geçen HTTP/S isteklerini burada gerçek zamanlı olarak izleyebilirsiniz.</p>
+            <HarControls />
             <div style={{ maxHeight: '75vh', overflowY: 'auto' }}>
                 <table>
                     <thead style={{ position: 'sticky', top: 0, background: 'white' }}>
                         <tr>
                             <th>Durum</th>
                             <th>Metot</th>
+                            <th>Protokol</th>
                             <th>URL</th>
                             <th>Boyut</th>
                             <th>Önbellek</th>
+                            <th>Kullanıcı</th>
                         </tr>
                     </thead>
                     <tbody>
                         {flows.value.length === 0 ? (
                         <tr>
-                            <td colSpan={5} style={{ textAlign: 'center', padding: '40px' }}>Henüz bir trafik algılanmadı...</td>
+                            <td colSpan={7} style={{ textAlign: 'center', padding: '40px' }}>Henüz bir trafik algılanmadı...</td>
                         </tr>
                         ) : (
                         flows.value.map(flow => (
-                            <tr key={flow.id}>
+                            <tr key={flow.id}
+                                onClick={() => setSelectedId(flow.id)}
+                                style={{ cursor: 'pointer', background: flow.id === selectedId ? '#eef5ff' : undefined }}>
                             <td><StatusBadge code={flow.statusCode} /></td>
                             <td>{flow.method}</td>
-                            <td class="url-cell" title={flow.uri}>{flow.uri}</td>
+                            <td>{flow.protocol}</td>
+                            <td class="url-cell" title={flow.error ? `${flow.uri}\n${flow.error}` : flow.uri}>
+                                {flow.uri}
+                                {flow.error && <div style={{ color: '#dc3545', fontSize: '0.8rem' }}>{flow.error}</div>}
+                            </td>
                             <td>{formatBytes(flow.responseSizeBytes)}</td>
-                            <td><HitBadge isHit={flow.isHit} /></td>
+                            <td><CacheCell flow={flow} /></td>
+                            <td>{flow.user ?? '—'}</td>
                             </tr>
                         ))
                         )}
@@ -67,6 +203,8 @@ export function NetworkFlow() {
                 </table>
             </div>
         </div>
+        {selected && <FlowDetails flow={selected} />}
+        {selected?.websocket && <FrameList flowId={selected.id} />}
     </div>
   );
 }
\ No newline at end of file
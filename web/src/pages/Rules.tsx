@@ -13,10 +13,26 @@ const formatCondition = (condition: RuleCondition) => {
 };
 
 const formatAction = (action: api.Action) => {
+    if (typeof action === 'object' && 'map-remote' in action) {
+        return { text: `YÖNLENDİR → ${action['map-remote']}`, color: '#20c997' };
+    }
+    if (typeof action === 'object' && 'map-local' in action) {
+        return { text: `YEREL DOSYA → ${action['map-local']}`, color: '#20c997' };
+    }
+    if (typeof action === 'object' && 'mock' in action) {
+        return { text: `SAHTE YANIT (${action.mock.status})`, color: '#e83e8c' };
+    }
+    if (typeof action === 'object' && 'rewrite-headers' in action) {
+        return { text: 'BAŞLIK DÜZENLE', color: '#fd7e14' };
+    }
+    if (typeof action === 'object') {
+        return { text: `HIZ SINIRI (${(action.throttle / 1024).toFixed(0)} KB/sn)`, color: '#6f42c1' };
+    }
     switch (action) {
         case 'Allow': return { text: 'İZİN VER', color: '#28a745' };
         case 'Block': return { text: 'ENGELLE', color: '#dc3545' };
         case 'BypassCache': return { text: 'ÖNBELLEĞİ ATLA', color: '#ffc107' };
+        case 'Tunnel': return { text: 'TLS TÜNELİ', color: '#17a2b8' };
         default: return { text: action, color: '#6c757d' };
     }
 };
@@ -47,12 +63,13 @@ export function Rules() {
                             <th>Kural Adı</th>
                             <th>Koşul</th>
                             <th>Eylem</th>
+                            <th>Kullanıcılar</th>
                         </tr>
                     </thead>
                     <tbody>
                         {rules.length === 0 && !error ? (
                             <tr>
-                                <td colSpan={3} style={{ textAlign: 'center', padding: '40px' }}>Aktif kural bulunamadı.</td>
+                                <td colSpan={4} style={{ textAlign: 'center', padding: '40px' }}>Aktif kural bulunamadı.</td>
                             </tr>
                         ) : (
                             rules.map(rule => {
@@ -66,6 +83,7 @@ export function Rules() {
                                                 {actionStyle.text}
                                             </span>
                                         </td>
+                                        <td>{rule.users?.length ? rule.users.join(', ') : 'Herkes'}</td>
                                     </tr>
                                 );
                             })
@@ -1,14 +1,17 @@
 import { signal } from "@preact/signals";
 import * as api from './api';
-import type { CacheStats, FlowEntry } from './api';
+import type { CacheStats, FlowEntry, WebSocketFrame } from './api';
 
 export const isConnected = signal(false);
 export const stats = signal<CacheStats>({
   hits: 0, misses: 0, totalRequests: 0, diskItems: 0, totalDiskSizeBytes: 0, bytesSaved: 0,
+  timeSavedMs: 0, upstream: [], rejectedClients: { proxy: 0, dns: 0, management: 0 },
 });
 export const flows = signal<FlowEntry[]>([]); // YENİ SİNYAL
+export const wsFrames = signal<WebSocketFrame[]>([]);
 
 const MAX_FLOWS = 100; // Ekranda en fazla kaç akış tutulacağı
+const MAX_WS_FRAMES = 500;
 
 function initializeStore() {
   api.fetchStats().then(data => { stats.value = data; }).catch(console.error);
@@ -17,6 +20,11 @@ function initializeStore() {
     onClose: () => { isConnected.value = false; },
     onStatsUpdated: (newStats) => { stats.value = newStats; },
     onFlowUpdated: (newFlow) => {
+      // WebSocket akışları açık kaldıkça aynı id ile güncellenir; yerinde değiştir.
+      if (flows.value.some(f => f.id === newFlow.id)) {
+        flows.value = flows.value.map(f => f.id === newFlow.id ? newFlow : f);
+        return;
+      }
       // Yeni akışı listenin başına ekle
       const updatedFlows = [newFlow, ...flows.value];
       // Listeyi belirli bir boyutta tut
@@ -25,6 +33,9 @@ function initializeStore() {
       }
       flows.value = updatedFlows;
     },
+    onWebSocketFrame: (frame) => {
+      wsFrames.value = [frame, ...wsFrames.value].slice(0, MAX_WS_FRAMES);
+    },
   });
 }
 